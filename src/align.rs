@@ -0,0 +1,119 @@
+use crate::error::GeometryError;
+use crate::float::Float;
+use crate::geometry::{Ion, bonded_angles, bonds};
+use crate::linalg::jacobi_eigen;
+
+// Unaligned RMSD over matched atom indices; the two geometries must already
+// be in correspondence and in the same frame
+pub fn rmsd(a: &[Ion], b: &[Ion]) -> Result<f64, GeometryError> {
+    check_correspondence(a, b)?;
+    let sum_sq: f64 = a.iter().zip(b).map(|(p, q)| p.bond_length(q).powi(2)).sum();
+    Ok((sum_sq/a.len() as f64).sqrt())
+}
+
+// Per-atom displacement b - a and its length, in index order. Like rmsd
+// this compares matched atoms as given, so align first to remove rigid
+// motion.
+pub fn displacement_vectors(a: &[Ion], b: &[Ion]) -> Result<Vec<(usize,[f64;3],f64)>, GeometryError> {
+    check_correspondence(a, b)?;
+    Ok(a.iter().zip(b).enumerate().map(|(i, (p, q))| (i, p.displacement(q), p.bond_length(q))).collect())
+}
+
+// displacement_vectors with the atoms that moved most first; ties keep
+// index order
+pub fn largest_displacements(a: &[Ion], b: &[Ion]) -> Result<Vec<(usize,[f64;3],f64)>, GeometryError> {
+    let mut displacements = displacement_vectors(a, b)?;
+    displacements.sort_by(|x, y| y.2.total_cmp(&x.2));
+    Ok(displacements)
+}
+
+// Change in each bond of `reference` (as found by bonds) going to `other`,
+// as (j,i,reference length,other - reference)
+pub fn bond_length_deltas(reference: &[Ion], other: &[Ion]) -> Result<Vec<(usize,usize,f64,f64)>, GeometryError> {
+    check_correspondence(reference, other)?;
+    Ok(bonds(reference)?.into_iter()
+        .map(|(j, i, l)| (j, i, l, other[j].bond_length(&other[i]) - l))
+        .collect())
+}
+
+// Change in each bonded angle of `reference` going to `other`, as
+// (vertex,a,b,reference angle,other - reference) in radians
+pub fn bond_angle_deltas(reference: &[Ion], other: &[Ion]) -> Result<Vec<(usize,usize,usize,f64,f64)>, GeometryError> {
+    check_correspondence(reference, other)?;
+    Ok(bonded_angles(reference)?.into_iter()
+        .map(|(j, a, b, theta)| (j, a, b, theta, Ion::bond_angle(&other[a], &other[j], &other[b]) - theta))
+        .collect())
+}
+
+// Two geometries of the same molecule: equal, non-zero length with the same
+// element at every index
+fn check_correspondence(a: &[Ion], b: &[Ion]) -> Result<(), GeometryError> {
+    if a.is_empty() {
+        return Err(GeometryError::TooFewAtoms { needed: 1, found: 0 });
+    }
+    if a.len() != b.len() {
+        return Err(GeometryError::AtomCountMismatch { declared: a.len(), found: b.len() });
+    }
+    for (i, (p, q)) in a.iter().zip(b).enumerate() {
+        if p.z_val != q.z_val {
+            return Err(GeometryError::ElementMismatch { index: i, a: p.z_val, b: q.z_val });
+        }
+    }
+    Ok(())
+}
+
+// Superimpose `mobile` onto `reference` by removing the centroid offset and
+// applying the rotation that minimises the RMSD (Horn's quaternion form of
+// the Kabsch problem). A unit quaternion always gives a proper rotation, so
+// the result is never reflected.
+pub fn kabsch_align(mobile: &[Ion], reference: &[Ion]) -> Result<Vec<Ion>, GeometryError> {
+    check_correspondence(mobile, reference)?;
+    let c_mob = mean_position(mobile);
+    let c_ref = mean_position(reference);
+
+    // correlation s[a][b] = sum of mobile_a * reference_b about the centroids
+    let mut s = [[0.;3];3];
+    for (p, q) in mobile.iter().zip(reference) {
+        let p = [p.x-c_mob.0, p.y-c_mob.1, p.z-c_mob.2];
+        let q = [q.x-c_ref.0, q.y-c_ref.1, q.z-c_ref.2];
+        for a in 0..3 {
+            for b in 0..3 {
+                s[a][b] += p[a]*q[b];
+            }
+        }
+    }
+    let [[sxx,sxy,sxz],[syx,syy,syz],[szx,szy,szz]] = s;
+    let n = [
+        [sxx+syy+szz, syz-szy,      szx-sxz,      sxy-syx     ],
+        [syz-szy,     sxx-syy-szz,  sxy+syx,      szx+sxz     ],
+        [szx-sxz,     sxy+syx,      -sxx+syy-szz, syz+szy     ],
+        [sxy-syx,     szx+sxz,      syz+szy,      -sxx-syy+szz],
+    ];
+
+    // the optimal rotation is the eigenvector of the largest eigenvalue
+    let (_, vectors) = jacobi_eigen(n);
+    let (q0, q1, q2, q3) = (vectors[0][3], vectors[1][3], vectors[2][3], vectors[3][3]);
+    let r = [
+        [q0*q0+q1*q1-q2*q2-q3*q3, 2.*(q1*q2-q0*q3),        2.*(q1*q3+q0*q2)       ],
+        [2.*(q1*q2+q0*q3),        q0*q0-q1*q1+q2*q2-q3*q3, 2.*(q2*q3-q0*q1)       ],
+        [2.*(q1*q3-q0*q2),        2.*(q2*q3+q0*q1),        q0*q0-q1*q1-q2*q2+q3*q3],
+    ];
+
+    Ok(mobile.iter().map(|ion| {
+        let p = [ion.x-c_mob.0, ion.y-c_mob.1, ion.z-c_mob.2];
+        let rot = |row: [f64;3]| row[0]*p[0] + row[1]*p[1] + row[2]*p[2];
+        Ion {
+            z_val: ion.z_val,
+            x: rot(r[0]) + c_ref.0,
+            y: rot(r[1]) + c_ref.1,
+            z: rot(r[2]) + c_ref.2,
+        }
+    }).collect())
+}
+
+// Unweighted mean of the atom positions
+pub(crate) fn mean_position<T: Float>(mol: &[Ion<T>]) -> (f64,f64,f64) {
+    let n = mol.len() as f64;
+    let sum = mol.iter().fold((0.,0.,0.), |acc, ion| (acc.0+ion.x.to_f64(), acc.1+ion.y.to_f64(), acc.2+ion.z.to_f64()));
+    (sum.0/n, sum.1/n, sum.2/n)
+}
@@ -0,0 +1,39 @@
+use crate::error::GeometryError;
+use crate::geometry::Ion;
+
+// 1 e A in debye
+pub const E_ANGSTROM_TO_DEBYE: f64 = 4.803204712570263;
+
+// Centroid weighted by nuclear charge z_val
+pub fn center_of_charge(mol: &[Ion]) -> Result<(f64,f64,f64), GeometryError> {
+    if mol.is_empty() {
+        return Err(GeometryError::TooFewAtoms { needed: 1, found: 0 });
+    }
+
+    let mut total = 0.;
+    let mut coc = (0.,0.,0.);
+    for ion in mol {
+        let q = ion.z_val as f64;
+        total += q;
+        coc.0 += q*ion.x;
+        coc.1 += q*ion.y;
+        coc.2 += q*ion.z;
+    }
+    if total == 0. {
+        return Err(GeometryError::ZeroTotalCharge);
+    }
+
+    Ok((coc.0/total, coc.1/total, coc.2/total))
+}
+
+// Dipole sum(q_i r_i) of point charges (one per ion, in e) in e A. For a
+// charged molecule the result depends on the coordinate origin.
+pub fn point_charge_dipole(mol: &[Ion], charges: &[f64]) -> Result<(f64,f64,f64), GeometryError> {
+    if charges.len() != mol.len() {
+        return Err(GeometryError::AtomCountMismatch { declared: mol.len(), found: charges.len() });
+    }
+
+    Ok(mol.iter().zip(charges).fold((0.,0.,0.), |mu, (ion, q)| {
+        (mu.0 + q*ion.x, mu.1 + q*ion.y, mu.2 + q*ion.z)
+    }))
+}
@@ -0,0 +1,411 @@
+use std::io;
+use std::io::Error;
+use std::io::ErrorKind;
+
+use crawford_group_projects::{GeometryError, Ion, Units};
+use crawford_group_projects::geometry::select_atoms;
+
+pub const USAGE: &str = "\
+usage: crawford-group-projects [OPTIONS] [FILE...]
+
+With no FILE, or when FILE is -, the geometry is read from standard input.
+Given several files, the human format prints a one-line summary of each
+(file, atom count, formula, shortest and longest bond) unless --detailed.
+XYZ, PDB (ATOM/HETATM records) and the legacy `Z x y z` format are accepted,
+gzip-compressed when built with the gzip feature.
+
+Analyses (default: all of them):
+    --lengths           all pairwise bond lengths
+    --angles            bond angles
+    --dihedrals         dihedral angles along bonded chains i-j-k-l
+    --com               center of mass, centroid and radius of gyration
+    --inertia           principal moments, rotational constants, rotor type
+    --all               every analysis above
+
+Options:
+    --units UNIT        units of the input coordinates: angstrom (default) or bohr;
+                        lengths are always reported in angstrom. Legacy
+                        `Z x y z` files such as data/acetaldehyde.dat (the
+                        Crawford project inputs) are in bohr and need
+                        --units bohr
+    --lenient           when an atom count disagrees with the atom records
+                        present, warn and read the records instead of failing
+    --frame N           analyse only frame N (0-based; -1 is the last) of a
+                        trajectory instead of every frame
+    --atoms LIST        restrict lengths, angles and dihedrals to these atoms,
+                        given as 0-based indices and ranges, e.g. 0,3,5-9
+    --precision N       decimal places in human-readable output (default 4)
+    --contacts DIST     with the length analysis, list every pair of atoms closer
+                        than DIST angstrom, bonded or not
+    --strain DEG        with the angle analysis, flag bonded angles more than DEG
+                        degrees from the VSEPR ideal for the vertex's
+                        coordination number (lone pairs are not counted)
+    --histogram DEG     with the angle analysis, count all bond angles in bins
+                        DEG degrees wide (at least 0.1)
+    --angle-min DEG     list only bond angles (all, bonded, linear and strained)
+                        of at least DEG degrees
+    --angle-max DEG     list only bond angles of at most DEG degrees
+    --degrees           report angles in degrees instead of radians
+    --format FORMAT     human (default), json (one object per frame), csv
+                        (bonds and bonded angles, in degrees) or dot (the
+                        bond graph, for Graphviz)
+    --compare REF       report the change in each bond length and bonded angle
+                        from the first geometry in REF (same atoms, same order)
+    --reference REF     align each geometry onto the first one in REF (same
+                        atoms, same order) and report the RMSD
+    --merge             analyse all the files as one system, their atoms in
+                        file order (e.g. a dimer from two monomer files)
+    --detailed          full report for every file, even when given several
+    --quiet             print only the selected analyses, not the molecule itself
+    --no-header         leave out section titles and indentation in human output,
+                        printing just the rows (one record per line, fields
+                        separated by whitespace) for awk and friends. Each
+                        section ends in a blank line, and sections that are
+                        normally skipped when empty are kept, so they always
+                        come in the same order
+    --verbose           print to stderr how long reading each file and each
+                        analysis took
+    --debug             also dump the parsed ions in debug form
+    -h, --help          print this message
+";
+
+// Narrowest --histogram bin in degrees: the bins print to one decimal
+// place, and a narrower width could ask for billions of them
+const MIN_HISTOGRAM_WIDTH: f64 = 0.1;
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum Format {
+    Human,
+    Json,
+    Csv,
+    Dot,
+}
+
+#[derive(Debug,Clone,PartialEq)]
+pub struct Options {
+    pub lengths: bool,
+    pub angles: bool,
+    pub dihedrals: bool,
+    pub com: bool,
+    pub inertia: bool,
+    pub units: Units,
+    pub atoms: Option<Vec<usize>>,
+    pub frame: Option<i64>,
+    pub lenient: bool,
+    pub format: Format,
+    pub contacts: Option<f64>,
+    pub strain: Option<f64>,
+    pub histogram: Option<f64>,
+    pub angle_min: Option<f64>,
+    pub angle_max: Option<f64>,
+    pub degrees: bool,
+    pub compare: Option<String>,
+    pub reference: Option<String>,
+    pub precision: usize,
+    pub merge: bool,
+    pub detailed: bool,
+    pub quiet: bool,
+    pub no_header: bool,
+    pub debug: bool,
+    pub verbose: bool,
+    pub help: bool,
+    pub files: Vec<String>,
+}
+
+impl Options {
+
+    pub fn parse<I: Iterator<Item=String>>(args: I) -> io::Result<Options> {
+        let mut opts = Options {
+            lengths: false,
+            angles: false,
+            dihedrals: false,
+            com: false,
+            inertia: false,
+            units: Units::Angstrom,
+            atoms: None,
+            frame: None,
+            lenient: false,
+            format: Format::Human,
+            contacts: None,
+            strain: None,
+            histogram: None,
+            angle_min: None,
+            angle_max: None,
+            degrees: false,
+            compare: None,
+            reference: None,
+            precision: 4,
+            merge: false,
+            detailed: false,
+            quiet: false,
+            no_header: false,
+            debug: false,
+            verbose: false,
+            help: false,
+            files: Vec::new(),
+        };
+        let mut any_analysis = false;
+
+        let mut args = args;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--lengths" => opts.lengths = true,
+                "--angles" => opts.angles = true,
+                "--dihedrals" => opts.dihedrals = true,
+                "--com" => opts.com = true,
+                "--inertia" => opts.inertia = true,
+                "--all" => {
+                    opts.set_all();
+                },
+                "--units" => {
+                    opts.units = match args.next() {
+                        Some(units) => units.parse().map_err(|_| invalid_arg(format!(
+                            "--units expects 'angstrom' or 'bohr', got '{}'", units
+                        )))?,
+                        None => return Err(invalid_arg("--units needs a value".to_string())),
+                    };
+                    continue;
+                },
+                "--atoms" => {
+                    opts.atoms = match args.next() {
+                        Some(list) => Some(parse_atom_list(&list).ok_or_else(|| invalid_arg(format!(
+                            "--atoms expects indices and ranges like 0,3,5-9, got '{}'", list
+                        )))?),
+                        None => return Err(invalid_arg("--atoms needs a value".to_string())),
+                    };
+                    continue;
+                },
+                "--frame" => {
+                    opts.frame = match args.next() {
+                        Some(n) => Some(n.parse().map_err(|_| invalid_arg(format!(
+                            "--frame expects a frame index such as 0 or -1, got '{}'", n
+                        )))?),
+                        None => return Err(invalid_arg("--frame needs a value".to_string())),
+                    };
+                    continue;
+                },
+                "--format" => {
+                    opts.format = match args.next().as_deref() {
+                        Some("human") => Format::Human,
+                        Some("json") => Format::Json,
+                        Some("csv") => Format::Csv,
+                        Some("dot") => Format::Dot,
+                        Some(other) => return Err(invalid_arg(format!(
+                            "--format expects 'human', 'json', 'csv' or 'dot', got '{}'", other
+                        ))),
+                        None => return Err(invalid_arg("--format needs a value".to_string())),
+                    };
+                    continue;
+                },
+                "--precision" => {
+                    opts.precision = match args.next() {
+                        Some(n) => n.parse().map_err(|_| invalid_arg(format!(
+                            "--precision expects a number of decimal places, got '{}'", n
+                        )))?,
+                        None => return Err(invalid_arg("--precision needs a value".to_string())),
+                    };
+                    continue;
+                },
+                "--contacts" => {
+                    opts.contacts = match args.next() {
+                        Some(dist) => Some(dist.parse().ok().filter(|d: &f64| d.is_finite() && *d > 0.).ok_or_else(|| invalid_arg(format!(
+                            "--contacts expects a positive distance in angstrom, got '{}'", dist
+                        )))?),
+                        None => return Err(invalid_arg("--contacts needs a value".to_string())),
+                    };
+                    continue;
+                },
+                "--strain" => {
+                    opts.strain = match args.next() {
+                        Some(deg) => Some(deg.parse().ok().filter(|d: &f64| *d >= 0.).ok_or_else(|| invalid_arg(format!(
+                            "--strain expects a non-negative angle in degrees, got '{}'", deg
+                        )))?),
+                        None => return Err(invalid_arg("--strain needs a value".to_string())),
+                    };
+                    continue;
+                },
+                "--histogram" => {
+                    opts.histogram = match args.next() {
+                        Some(deg) => Some(deg.parse().ok().filter(|d: &f64| d.is_finite() && *d >= MIN_HISTOGRAM_WIDTH).ok_or_else(|| invalid_arg(format!(
+                            "--histogram expects a bin width of at least {} degrees, got '{}'", MIN_HISTOGRAM_WIDTH, deg
+                        )))?),
+                        None => return Err(invalid_arg("--histogram needs a value".to_string())),
+                    };
+                    continue;
+                },
+                "--angle-min" | "--angle-max" => {
+                    let bound = match args.next() {
+                        Some(deg) => deg.parse().ok().filter(|d: &f64| d.is_finite()).ok_or_else(|| invalid_arg(format!(
+                            "{} expects an angle in degrees, got '{}'", arg, deg
+                        )))?,
+                        None => return Err(invalid_arg(format!("{} needs a value", arg))),
+                    };
+                    if arg == "--angle-min" {
+                        opts.angle_min = Some(bound);
+                    } else {
+                        opts.angle_max = Some(bound);
+                    }
+                    continue;
+                },
+                "--degrees" => {
+                    opts.degrees = true;
+                    continue;
+                },
+                "--compare" => {
+                    match args.next() {
+                        Some(file) => opts.compare = Some(file),
+                        None => return Err(invalid_arg("--compare needs a file".to_string())),
+                    }
+                    continue;
+                },
+                "--reference" => {
+                    match args.next() {
+                        Some(file) => opts.reference = Some(file),
+                        None => return Err(invalid_arg("--reference needs a file".to_string())),
+                    }
+                    continue;
+                },
+                "--lenient" => {
+                    opts.lenient = true;
+                    continue;
+                },
+                "--merge" => {
+                    opts.merge = true;
+                    continue;
+                },
+                "--detailed" => {
+                    opts.detailed = true;
+                    continue;
+                },
+                "--quiet" => {
+                    opts.quiet = true;
+                    continue;
+                },
+                "--no-header" => {
+                    opts.no_header = true;
+                    continue;
+                },
+                "--debug" => {
+                    opts.debug = true;
+                    continue;
+                },
+                "--verbose" => {
+                    opts.verbose = true;
+                    continue;
+                },
+                "-h" | "--help" => {
+                    opts.help = true;
+                    continue;
+                },
+                flag if flag.starts_with("--") => {
+                    return Err(invalid_arg(format!("unknown option '{}'", flag)));
+                },
+                _ => {
+                    opts.files.push(arg);
+                    continue;
+                },
+            }
+            any_analysis = true;
+        }
+
+        if let (Some(min), Some(max)) = (opts.angle_min, opts.angle_max) {
+            if min > max {
+                return Err(invalid_arg(format!("--angle-min {} is above --angle-max {}", min, max)));
+            }
+        }
+
+        // read a single geometry from stdin when no file is named
+        if opts.files.is_empty() {
+            opts.files.push("-".to_string());
+        }
+
+        // no analysis selected keeps the old print-everything behaviour
+        if !any_analysis {
+            opts.set_all();
+        }
+
+        Ok(opts)
+    }
+
+    // Several files are summarised one line each unless --detailed, or
+    // --merge makes them one system
+    pub fn batch(&self) -> bool {
+        self.files.len() > 1 && !self.detailed && !self.merge
+    }
+
+    // The --frame index into a trajectory of `nframes` frames, counting
+    // negative indices back from the end
+    pub fn frame_index(&self, nframes: usize) -> Option<usize> {
+        let n = self.frame?;
+        let index = if n < 0 { nframes as i64 + n } else { n };
+        if (0..nframes as i64).contains(&index) { Some(index as usize) } else { None }
+    }
+
+    // Whether an angle in radians is inside the --angle-min/--angle-max range
+    pub fn angle_in_range(&self, radians: f64) -> bool {
+        let degrees = radians.to_degrees();
+        self.angle_min.is_none_or(|min| degrees >= min) && self.angle_max.is_none_or(|max| degrees <= max)
+    }
+
+    // Angles come out of the library in radians and are only converted here,
+    // at the reporting boundary
+    pub fn angle(&self, radians: f64) -> f64 {
+        if self.degrees {
+            radians.to_degrees()
+        } else {
+            radians
+        }
+    }
+
+    // Fixed-point with --precision decimal places, for the human format
+    pub fn number(&self, x: f64) -> String {
+        format!("{:.*}", self.precision, x)
+    }
+
+    pub fn angle_units(&self) -> &'static str {
+        if self.degrees { "degrees" } else { "radians" }
+    }
+
+    // The atoms the per-atom analyses run over and their indices in `mol`:
+    // the --atoms selection, or the whole molecule
+    pub fn selected(&self, mol: &[Ion]) -> Result<(Vec<Ion>, Vec<usize>), GeometryError> {
+        match &self.atoms {
+            Some(atoms) => Ok((select_atoms(mol, atoms)?, atoms.clone())),
+            None => Ok((mol.to_vec(), (0..mol.len()).collect())),
+        }
+    }
+
+    fn set_all(&mut self) {
+        self.lengths = true;
+        self.angles = true;
+        self.dihedrals = true;
+        self.com = true;
+        self.inertia = true;
+    }
+
+}
+
+// Comma-separated indices and inclusive ranges, e.g. "0,3,5-9", as sorted
+// unique indices
+fn parse_atom_list(list: &str) -> Option<Vec<usize>> {
+    let mut atoms = Vec::new();
+    for item in list.split(',') {
+        match item.split_once('-') {
+            Some((first, last)) => {
+                let (first, last): (usize, usize) = (first.trim().parse().ok()?, last.trim().parse().ok()?);
+                if first > last {
+                    return None;
+                }
+                atoms.extend(first..=last);
+            },
+            None => atoms.push(item.trim().parse().ok()?),
+        }
+    }
+    atoms.sort_unstable();
+    atoms.dedup();
+    Some(atoms)
+}
+
+fn invalid_arg(msg: String) -> Error {
+    Error::new(ErrorKind::InvalidInput, format!("{} (see --help)", msg))
+}
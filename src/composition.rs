@@ -0,0 +1,59 @@
+use std::collections::BTreeMap;
+
+use crate::elements::{mass_for_z, symbol_for_z};
+use crate::error::GeometryError;
+use crate::geometry::Ion;
+
+// Sum of standard atomic weights, in amu
+pub fn molecular_mass(mol: &[Ion]) -> Result<f64, GeometryError> {
+    mol.iter()
+        .map(|ion| mass_for_z(ion.z_val).ok_or(GeometryError::UnknownElement(ion.z_val)))
+        .sum()
+}
+
+// Hill notation: C first, then H, then everything else alphabetically; with
+// no carbon every element is alphabetical. Counts of one are omitted and
+// atoms without a known symbol are written as Z<n>.
+pub fn molecular_formula(mol: &[Ion]) -> String {
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for ion in mol {
+        let sym = symbol_for_z(ion.z_val).map_or_else(|| format!("Z{}", ion.z_val), String::from);
+        *counts.entry(sym).or_insert(0) += 1;
+    }
+
+    let mut formula = String::new();
+    let mut push = |sym: &str, n: usize| {
+        formula.push_str(sym);
+        if n > 1 {
+            formula.push_str(&n.to_string());
+        }
+    };
+
+    if let Some(c) = counts.remove("C") {
+        push("C", c);
+        if let Some(h) = counts.remove("H") {
+            push("H", h);
+        }
+    }
+    for (sym, n) in &counts {
+        push(sym, *n);
+    }
+
+    formula
+}
+
+// (Z, number of atoms) for each element present, in order of Z
+pub fn element_counts(mol: &[Ion]) -> Vec<(i32, usize)> {
+    let mut counts: BTreeMap<i32, usize> = BTreeMap::new();
+    for ion in mol {
+        *counts.entry(ion.z_val).or_insert(0) += 1;
+    }
+    counts.into_iter().collect()
+}
+
+// m_a m_b/(m_a + m_b) from standard atomic weights, in amu
+pub fn reduced_mass(a: &Ion, b: &Ion) -> Result<f64, GeometryError> {
+    let m_a = mass_for_z(a.z_val).ok_or(GeometryError::UnknownElement(a.z_val))?;
+    let m_b = mass_for_z(b.z_val).ok_or(GeometryError::UnknownElement(b.z_val))?;
+    Ok(m_a*m_b/(m_a+m_b))
+}
@@ -0,0 +1,30 @@
+use crawford_group_projects::Ion;
+use crawford_group_projects::geometry::{bonded_angles, bonds};
+
+use crate::cli::Options;
+use crate::table::element_label;
+
+// Bonds and bonded angles as CSV tables for spreadsheets, each with a header
+// row and separated by a blank line. Like the JSON report, atoms are indexed
+// as in the whole molecule even with --atoms. Angles are always in degrees,
+// as the header says, and keep to any --angle-min/--angle-max range. Fields
+// are numbers and element symbols, so nothing needs quoting.
+pub fn report(mol: &[Ion], (sel, index): &(Vec<Ion>, Vec<usize>), opts: &Options) -> String {
+    let mut tables = Vec::new();
+    if opts.lengths {
+        let mut table = String::from("i,j,element_i,element_j,length\n");
+        for (j, i, l) in bonds(sel).unwrap_or_default() {
+            let (j, i) = (index[j], index[i]);
+            table.push_str(&format!("{},{},{},{},{}\n", j, i, element_label(mol[j].z_val), element_label(mol[i].z_val), l));
+        }
+        tables.push(table);
+    }
+    if opts.angles {
+        let mut table = String::from("i,j,k,degrees\n");
+        for (j, a, b, theta) in bonded_angles(sel).unwrap_or_default().into_iter().filter(|a| opts.angle_in_range(a.3)) {
+            table.push_str(&format!("{},{},{},{}\n", index[a], index[j], index[b], theta.to_degrees()));
+        }
+        tables.push(table);
+    }
+    tables.join("\n")
+}
@@ -0,0 +1,112 @@
+use std::fmt;
+
+// Element symbols indexed by atomic number - 1, H through Kr
+const SYMBOLS: [&str; 36] = [
+    "H",                                                  "He",
+    "Li","Be",                     "B", "C", "N", "O", "F", "Ne",
+    "Na","Mg",                     "Al","Si","P", "S", "Cl","Ar",
+    "K", "Ca","Sc","Ti","V", "Cr","Mn","Fe","Co","Ni","Cu","Zn",
+    "Ga","Ge","As","Se","Br","Kr",
+];
+
+pub fn symbol_for_z(z: i32) -> Option<&'static str> {
+    if z <= 0 {
+        None
+    } else {
+        SYMBOLS.get((z-1) as usize).copied()
+    }
+}
+
+pub fn z_for_symbol(sym: &str) -> Option<i32> {
+    SYMBOLS.iter()
+        .position(|s| s.eq_ignore_ascii_case(sym))
+        .map(|i| i as i32 + 1)
+}
+
+// Standard (isotope-averaged) atomic weights in amu, indexed like SYMBOLS
+const MASSES: [f64; 36] = [
+    1.008,       4.002602,    6.94,        9.0121831,   10.81,
+    12.011,      14.007,      15.999,      18.998403163,20.1797,
+    22.98976928, 24.305,      26.9815385,  28.085,      30.973761998,
+    32.06,       35.45,       39.948,      39.0983,     40.078,
+    44.955908,   47.867,      50.9415,     51.9961,     54.938044,
+    55.845,      58.933194,   58.6934,     63.546,      65.38,
+    69.723,      72.630,      74.921595,   78.971,      79.904,
+    83.798,
+];
+
+pub fn mass_for_z(z: i32) -> Option<f64> {
+    if z <= 0 {
+        None
+    } else {
+        MASSES.get((z-1) as usize).copied()
+    }
+}
+
+// Exact isotope masses in amu (AME2020) as (Z, mass number, mass), for the
+// isotopes that commonly replace the standard atomic weight
+const ISOTOPE_MASSES: [(i32, u32, f64); 22] = [
+    (1, 1, 1.00782503223),
+    (1, 2, 2.01410177812),
+    (1, 3, 3.01604928132),
+    (3, 7, 7.0160034366),
+    (5, 10, 10.01293695),
+    (5, 11, 11.00930536),
+    (6, 12, 12.0),
+    (6, 13, 13.00335483507),
+    (7, 14, 14.00307400443),
+    (7, 15, 15.00010889888),
+    (8, 16, 15.99491461957),
+    (8, 17, 16.99913175650),
+    (8, 18, 17.99915961286),
+    (9, 19, 18.99840316273),
+    (14, 28, 27.97692653465),
+    (15, 31, 30.97376199842),
+    (16, 32, 31.9720711744),
+    (16, 34, 33.967867004),
+    (17, 35, 34.968852682),
+    (17, 37, 36.965902602),
+    (35, 79, 78.9183376),
+    (35, 81, 80.9162897),
+];
+
+pub fn isotope_mass(z: i32, mass_number: u32) -> Option<f64> {
+    ISOTOPE_MASSES.iter()
+        .find(|&&(iz, a, _)| iz == z && a == mass_number)
+        .map(|&(_, _, m)| m)
+}
+
+// Covalent radii in angstrom (Cordero et al. 2008), indexed like SYMBOLS.
+// Where several are given the sp3 carbon and low-spin metal values are used.
+const COVALENT_RADII: [f64; 36] = [
+    0.31, 0.28, 1.28, 0.96, 0.84, 0.76, 0.71, 0.66, 0.57, 0.58,
+    1.66, 1.41, 1.21, 1.11, 1.07, 1.05, 1.02, 1.06, 2.03, 1.76,
+    1.70, 1.60, 1.53, 1.39, 1.39, 1.32, 1.26, 1.24, 1.32, 1.22,
+    1.22, 1.20, 1.19, 1.20, 1.20, 1.16,
+];
+
+pub fn covalent_radius_for_z(z: i32) -> Option<f64> {
+    if z <= 0 {
+        None
+    } else {
+        COVALENT_RADII.get((z-1) as usize).copied()
+    }
+}
+
+// Element symbol where known, otherwise the raw atomic number
+pub(crate) struct Element(pub(crate) i32);
+
+impl fmt::Debug for Element {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl fmt::Display for Element {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match symbol_for_z(self.0) {
+            Some(sym) => f.pad(sym),
+            None => f.pad(&self.0.to_string()),
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use std::error;
+use std::fmt;
+use std::io;
+
+use crate::elements::symbol_for_z;
+
+#[derive(Debug,Clone,PartialEq)]
+pub enum GeometryError {
+    // declared atom count against the atom records actually present
+    AtomCountMismatch { declared: usize, found: usize },
+    // a trajectory frame that runs off the end of the file
+    TruncatedFrame { line: usize, declared: usize, found: usize },
+    // lines are 1-based
+    MissingField { line: usize, field: &'static str },
+    ParseField { line: usize, field: &'static str, token: String },
+    TooFewAtoms { needed: usize, found: usize },
+    UnknownElement(i32),
+    UnknownIsotope { z: i32, mass_number: u32 },
+    UnknownSymbol(String),
+    NonFiniteCoordinate { index: usize, axis: char, value: f64 },
+    CoincidentAtoms(usize, usize),
+    ElementMismatch { index: usize, a: i32, b: i32 },
+    UnknownUnits(String),
+    ZeroTotalCharge,
+    // lattice vectors that do not span three dimensions
+    DegenerateCell,
+    AtomIndexOutOfRange { index: usize, natoms: usize },
+    // a-vertex-b with the angle at 0 or pi, where its derivative is undefined
+    LinearAngle(usize, usize, usize),
+    // the same for atoms passed by reference rather than by index
+    CollinearAtoms,
+    // a distance cutoff that is negative, infinite or NaN
+    InvalidCutoff(f64),
+}
+
+// symbol where known, otherwise Z
+fn element(z: i32) -> String {
+    symbol_for_z(z).map_or_else(|| format!("Z = {}", z), String::from)
+}
+
+impl fmt::Display for GeometryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GeometryError::AtomCountMismatch { declared, found } => write!(
+                f, "Number of atoms != number of data points ({} declared, {} found)", declared, found
+            ),
+            GeometryError::TruncatedFrame { line, declared, found } => write!(
+                f, "line {}: frame declares {} atoms but only {} lines follow", line, declared, found
+            ),
+            GeometryError::MissingField { line, field } => write!(
+                f, "line {}: missing {}", line, field
+            ),
+            GeometryError::ParseField { line, field, token } => {
+                write!(f, "line {}: invalid {} '{}'", line, field, token)?;
+                // "1,234" is usually a decimal comma from a localized program
+                if token.contains(',') {
+                    write!(f, " (numbers need '.' as the decimal separator; was the file written with a comma-decimal locale?)")?;
+                }
+                Ok(())
+            },
+            GeometryError::TooFewAtoms { needed, found } => write!(
+                f, "too few ions: need at least {}, got {}", needed, found
+            ),
+            GeometryError::UnknownElement(z) => write!(
+                f, "no element data for Z = {}", z
+            ),
+            GeometryError::UnknownIsotope { z, mass_number } => write!(
+                f, "no mass data for {}-{}", element(*z), mass_number
+            ),
+            GeometryError::UnknownSymbol(sym) => write!(
+                f, "unknown element symbol '{}'", sym
+            ),
+            GeometryError::NonFiniteCoordinate { index, axis, value } => write!(
+                f, "ion {} has non-finite {} coordinate {}", index, axis, value
+            ),
+            GeometryError::CoincidentAtoms(i, j) => write!(
+                f, "ions {} and {} are at the same position", i, j
+            ),
+            GeometryError::ElementMismatch { index, a, b } => write!(
+                f, "element mismatch at ion {} ({} and {})", index, element(*a), element(*b)
+            ),
+            GeometryError::UnknownUnits(units) => write!(
+                f, "unknown units '{}'", units
+            ),
+            GeometryError::ZeroTotalCharge => write!(
+                f, "total charge is zero, so the center of charge is undefined"
+            ),
+            GeometryError::DegenerateCell => write!(
+                f, "unit cell has zero volume"
+            ),
+            GeometryError::AtomIndexOutOfRange { index, natoms } => write!(
+                f, "ion index {} out of range for {} ions", index, natoms
+            ),
+            GeometryError::LinearAngle(a, j, b) => write!(
+                f, "ions {}-{}-{} are collinear", a, j, b
+            ),
+            GeometryError::CollinearAtoms => write!(
+                f, "atoms are collinear, so the quantity is undefined"
+            ),
+            GeometryError::InvalidCutoff(cutoff) => write!(
+                f, "invalid distance cutoff {}", cutoff
+            ),
+        }
+    }
+}
+
+impl error::Error for GeometryError {}
+
+impl From<GeometryError> for io::Error {
+    fn from(err: GeometryError) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
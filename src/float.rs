@@ -0,0 +1,56 @@
+use std::fmt;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+// The floating-point operations the geometry code needs, so Ion and the
+// angle functions work for both f32 and f64 without an external crate
+pub trait Float:
+    Copy + PartialOrd + fmt::Debug + fmt::Display
+    + Add<Output=Self> + Sub<Output=Self> + Mul<Output=Self> + Div<Output=Self> + Neg<Output=Self>
+{
+    fn zero() -> Self;
+    fn one() -> Self;
+    fn nan() -> Self;
+    fn from_f64(x: f64) -> Self;
+    fn to_f64(self) -> f64;
+    fn sqrt(self) -> Self;
+    fn abs(self) -> Self;
+    fn sin(self) -> Self;
+    fn acos(self) -> Self;
+    fn asin(self) -> Self;
+    fn atan2(self, other: Self) -> Self;
+    fn is_nan(self) -> bool;
+    fn is_finite(self) -> bool;
+
+    fn clamp_unit(self) -> Self {
+        if self > Self::one() {
+            Self::one()
+        } else if self < -Self::one() {
+            -Self::one()
+        } else {
+            self
+        }
+    }
+}
+
+macro_rules! impl_float {
+    ($t:ty) => {
+        impl Float for $t {
+            fn zero() -> Self { 0. }
+            fn one() -> Self { 1. }
+            fn nan() -> Self { <$t>::NAN }
+            fn from_f64(x: f64) -> Self { x as $t }
+            fn to_f64(self) -> f64 { self as f64 }
+            fn sqrt(self) -> Self { <$t>::sqrt(self) }
+            fn abs(self) -> Self { <$t>::abs(self) }
+            fn sin(self) -> Self { <$t>::sin(self) }
+            fn acos(self) -> Self { <$t>::acos(self) }
+            fn asin(self) -> Self { <$t>::asin(self) }
+            fn atan2(self, other: Self) -> Self { <$t>::atan2(self, other) }
+            fn is_nan(self) -> bool { <$t>::is_nan(self) }
+            fn is_finite(self) -> bool { <$t>::is_finite(self) }
+        }
+    };
+}
+
+impl_float!(f32);
+impl_float!(f64);
@@ -0,0 +1,692 @@
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::fmt;
+
+use crate::elements::{Element, covalent_radius_for_z, z_for_symbol};
+use crate::error::GeometryError;
+use crate::float::Float;
+use crate::grid::{CellGrid, GRID_MIN_ATOMS};
+use crate::linalg::{cross, dot};
+
+// Coordinates default to f64; the geometry methods also work for Ion<f32>
+#[derive(Clone,Copy,PartialEq)]
+pub struct Ion<T = f64> {
+    pub z_val: i32,
+    pub x: T,
+    pub y: T,
+    pub z: T,
+}
+
+impl<T: Float> fmt::Debug for Ion<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Ion")
+            .field("z_val", &Element(self.z_val))
+            .field("x", &self.x)
+            .field("y", &self.y)
+            .field("z", &self.z)
+            .finish()
+    }
+}
+
+impl<T: Float> fmt::Display for Ion<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?} {} {} {}", Element(self.z_val), self.x, self.y, self.z)
+    }
+}
+
+impl<T: Float> Ion<T> {
+
+    // e.g. Ion::from_symbol("O", 0., 0., 0.1173); symbols are case-insensitive
+    pub fn from_symbol(symbol: &str, x: T, y: T, z: T) -> Result<Ion<T>, GeometryError> {
+        let z_val = z_for_symbol(symbol).ok_or_else(|| GeometryError::UnknownSymbol(symbol.to_string()))?;
+        Ok(Ion { z_val, x, y, z })
+    }
+
+    pub fn bond_length(&self, other: &Ion<T>) -> T {
+        self.bond_length_sq(other).sqrt()
+    }
+
+    // Square of bond_length, for cutoff tests that need no square root
+    pub fn bond_length_sq(&self, other: &Ion<T>) -> T {
+        let d = self.displacement(other);
+        dot(d,d)
+    }
+
+    // Vector from self to other, as used by the rest of the vector math
+    pub fn displacement(&self, other: &Ion<T>) -> [T;3] {
+        [other.x-self.x,other.y-self.y,other.z-self.z]
+    }
+
+    // displacement as a tuple, kept for existing callers
+    pub fn bond_vector(&self, other: &Ion<T>) -> (T,T,T) {
+        let [dx, dy, dz] = self.displacement(other);
+        (dx,dy,dz)
+    }
+
+    // Derivatives of the bond length with respect to the coordinates of
+    // self and other: the unit vector from other to self, and its negative.
+    // The gradient is undefined for coincident atoms, which get zeros.
+    pub fn bond_length_gradient(&self, other: &Ion<T>) -> ([T;3],[T;3]) {
+        let [dx, dy, dz] = self.displacement(other);
+        let l = self.bond_length(other);
+        if l == T::zero() {
+            return ([T::zero();3],[T::zero();3]);
+        }
+        ([-dx/l,-dy/l,-dz/l],[dx/l,dy/l,dz/l])
+    }
+
+    pub fn bond_angle(ioni: &Ion<T>, ionj: &Ion<T>, ionk: &Ion<T>) -> T {
+        let r = |ion: &Ion<T>| [ion.x, ion.y, ion.z];
+        angle_between(r(ioni), r(ionj), r(ionk))
+    }
+
+    // Derivatives of bond_angle(ioni, ionj, ionk) with respect to the
+    // coordinates of i, j and k (Wilson's s-vectors). They diverge as the
+    // angle approaches 0 or pi, which is an error.
+    pub fn bond_angle_gradient(ioni: &Ion<T>, ionj: &Ion<T>, ionk: &Ion<T>) -> Result<([T;3],[T;3],[T;3]), GeometryError> {
+        let l_ji = ionj.bond_length(ioni);
+        let l_jk = ionj.bond_length(ionk);
+        let u = ionj.displacement(ioni).map(|c| c/l_ji);
+        let v = ionj.displacement(ionk).map(|c| c/l_jk);
+        let cos = dot(u,v);
+        let n = cross(u,v);
+        let sin = dot(n,n).sqrt();
+        if sin <= T::from_f64(COLLINEAR_TOL) || sin.is_nan() {
+            return Err(GeometryError::CollinearAtoms);
+        }
+
+        let mut gi = [T::zero();3];
+        let mut gj = [T::zero();3];
+        let mut gk = [T::zero();3];
+        for c in 0..3 {
+            gi[c] = (u[c]*cos - v[c])/(l_ji*sin);
+            gk[c] = (v[c]*cos - u[c])/(l_jk*sin);
+            gj[c] = -gi[c] - gk[c];
+        }
+        Ok((gi, gj, gk))
+    }
+
+    // Angle between the i-k bond and the j-k-l plane. The plane is
+    // undefined when j, k and l are collinear, which is an error.
+    pub fn out_of_plane_angle(ioni: &Ion<T>,ionj: &Ion<T>,ionk: &Ion<T>,ionl: &Ion<T>) -> Result<T, GeometryError> {
+        Ok(Ion::out_of_plane_sine(ioni,ionj,ionk,ionl)?.clamp_unit().asin())
+    }
+
+    // Sine of out_of_plane_angle, unclamped; swapping j and l flips its sign.
+    pub fn out_of_plane_sine(ioni: &Ion<T>,ionj: &Ion<T>,ionk: &Ion<T>,ionl: &Ion<T>) -> Result<T, GeometryError> {
+        let sin_phi_jkl = Ion::bond_angle(ionj,ionk,ionl).sin();
+        if sin_phi_jkl.abs() <= T::from_f64(COLLINEAR_TOL) || sin_phi_jkl.is_nan() {
+            return Err(GeometryError::CollinearAtoms);
+        }
+        let r_kj = ionk.displacement(ionj);
+        let r_kl = ionk.displacement(ionl);
+        let r_ki = ionk.displacement(ioni);
+        let l_kj = ionk.bond_length(ionj);
+        let l_kl = ionk.bond_length(ionl);
+        let l_ki = ionk.bond_length(ioni);
+        let scalar_triple = dot(r_ki, cross(r_kj,r_kl));
+        Ok(scalar_triple/(l_kj*l_kl*l_ki*sin_phi_jkl))
+    }
+
+    // Signed torsion about the j-k bond in (-pi, pi], trans = +-pi.
+    // Returns NaN when i-j-k or j-k-l are collinear (torsion undefined).
+    pub fn dihedral_angle(ioni: &Ion<T>, ionj: &Ion<T>, ionk: &Ion<T>, ionl: &Ion<T>) -> T {
+        let b1 = ioni.displacement(ionj);
+        let b2 = ionj.displacement(ionk);
+        let b3 = ionk.displacement(ionl);
+        let n1 = cross(b1,b2);
+        let n2 = cross(b2,b3);
+        let l_ij = ioni.bond_length(ionj);
+        let l_jk = ionj.bond_length(ionk);
+        let l_kl = ionk.bond_length(ionl);
+        let tol = T::from_f64(COLLINEAR_TOL);
+        // |n| = l*l*sin(phi), so compare the sines of the two bond angles
+        if dot(n1,n1).sqrt() <= tol*l_ij*l_jk
+        || dot(n2,n2).sqrt() <= tol*l_jk*l_kl {
+            return T::nan();
+        }
+        (l_jk*dot(b1,n2)).atan2(dot(n1,n2))
+    }
+
+}
+
+// Angle a-b-c at vertex b in radians, for bare coordinates; NaN when b
+// coincides with a or c
+pub fn angle_between<T: Float>(a: [T;3], b: [T;3], c: [T;3]) -> T {
+    let e_ba = [a[0]-b[0], a[1]-b[1], a[2]-b[2]];
+    let e_bc = [c[0]-b[0], c[1]-b[1], c[2]-b[2]];
+    // rounding can push the cosine just past +-1 for near-linear angles
+    (dot(e_ba,e_bc)
+    /(dot(e_ba,e_ba).sqrt()*dot(e_bc,e_bc).sqrt())).clamp_unit().acos()
+}
+
+// sin(phi) below which three atoms are treated as collinear
+const COLLINEAR_TOL: f64 = 1e-8;
+
+// Symmetric matrix of all pairwise distances. Each pair is computed once
+// and stored in both halves, so lengths[i][j] and lengths[j][i] are
+// bit-for-bit equal, and the diagonal is exactly zero.
+pub fn all_bond_lengths<T: Float>(mol: &[Ion<T>]) -> Result<Vec<Vec<T>>, GeometryError> {
+    if mol.len() <= 1 {
+        Err(GeometryError::TooFewAtoms { needed: 2, found: mol.len() })
+
+    } else {
+        let mut lengths = vec![vec![T::zero();mol.len()];mol.len()];
+
+        for i in 0..mol.len() {
+            for j in 0..i {
+                let l = mol[i].bond_length(&mol[j]);
+                lengths[i][j] = l;
+                lengths[j][i] = l;
+            }
+        }
+
+        debug_assert!((0..mol.len()).all(|i| {
+            lengths[i][i] == T::zero() && (0..i).all(|j| lengths[i][j].to_f64().to_bits() == lengths[j][i].to_f64().to_bits())
+        }));
+        Ok(lengths)
+    }
+}
+
+// The strict upper triangle of all_bond_lengths, row by row: n(n-1)/2
+// values instead of n^2, with the pair i<j at triangle_index(i, j, n)
+pub fn bond_length_triangle<T: Float>(mol: &[Ion<T>]) -> Result<Vec<T>, GeometryError> {
+    if mol.len() <= 1 {
+        return Err(GeometryError::TooFewAtoms { needed: 2, found: mol.len() });
+    }
+    Ok(bond_length_pairs(mol).map(|(_, _, l)| l).collect())
+}
+
+// Position of the pair i<j among the n(n-1)/2 entries of bond_length_triangle
+pub fn triangle_index(i: usize, j: usize, n: usize) -> usize {
+    debug_assert!(i < j && j < n);
+    i*(2*n-i-1)/2 + j-i-1
+}
+
+// Each unique pair (i,j,length) with i<j, row by row through the upper
+// triangle of all_bond_lengths, computed as it is consumed
+pub fn bond_length_pairs<T: Float>(mol: &[Ion<T>]) -> impl Iterator<Item=(usize,usize,T)> + '_ {
+    (0..mol.len()).flat_map(move |i| {
+        (i+1..mol.len()).map(move |j| (i,j,mol[i].bond_length(&mol[j])))
+    })
+}
+
+// The closest and the farthest pair (i,j,length), i<j. Ties go to the
+// pair that comes first in bond_length_pairs order.
+pub fn distance_extrema<T: Float>(mol: &[Ion<T>]) -> Result<((usize,usize,T),(usize,usize,T)), GeometryError> {
+    let mut pairs = bond_length_pairs(mol);
+    let first = pairs.next().ok_or(GeometryError::TooFewAtoms { needed: 2, found: mol.len() })?;
+    Ok(pairs.fold((first, first), |(min, max), pair| {
+        (if pair.2 < min.2 { pair } else { min }, if pair.2 > max.2 { pair } else { max })
+    }))
+}
+
+// Each atom's closest other atom as (index, distance), ties going to the
+// lower index. Needs no covalent radii, so it works for any element. Large
+// molecules are searched through a cell list, with the same result.
+pub fn nearest_neighbor<T: Float>(mol: &[Ion<T>]) -> Result<Vec<(usize,T)>, GeometryError> {
+    if mol.len() <= 1 {
+        return Err(GeometryError::TooFewAtoms { needed: 2, found: mol.len() });
+    }
+    if mol.len() >= GRID_MIN_ATOMS {
+        if let Some(grid) = CellGrid::for_nearest(mol) {
+            return Ok((0..mol.len()).filter_map(|a| grid.nearest(mol, a)).collect());
+        }
+    }
+    let mut nearest: Vec<Option<(usize,T)>> = vec![None; mol.len()];
+    for (i, j, l) in bond_length_pairs(mol) {
+        for (a, b) in [(i, j), (j, i)] {
+            if nearest[a].is_none_or(|(_, d)| l < d) {
+                nearest[a] = Some((b, l));
+            }
+        }
+    }
+    Ok(nearest.into_iter().flatten().collect())
+}
+
+// Same matrix as all_bond_lengths, with rows split across threads. Each
+// entry is the same single bond_length call, so the result is bit-for-bit
+// identical to the serial version.
+#[cfg(feature = "parallel")]
+pub fn all_bond_lengths_par<T: Float + Send + Sync>(mol: &[Ion<T>]) -> Result<Vec<Vec<T>>, GeometryError> {
+    if mol.len() <= 1 {
+        return Err(GeometryError::TooFewAtoms { needed: 2, found: mol.len() });
+    }
+
+    let n_threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let rows_per_thread = mol.len().div_ceil(n_threads);
+    let mut lengths = vec![vec![T::zero();mol.len()];mol.len()];
+
+    std::thread::scope(|scope| {
+        for (chunk, rows) in lengths.chunks_mut(rows_per_thread).enumerate() {
+            scope.spawn(move || {
+                for (n, row) in rows.iter_mut().enumerate() {
+                    let i = chunk*rows_per_thread + n;
+                    for (j, l) in row.iter_mut().enumerate() {
+                        if i != j {
+                            *l = mol[i].bond_length(&mol[j]);
+                        }
+                    }
+                }
+            });
+        }
+    });
+
+    Ok(lengths)
+}
+
+// The atoms at `atoms`, in that order, for running any of the analyses on
+// part of a molecule; index n of the result is atom atoms[n] of `mol`
+pub fn select_atoms<T: Float>(mol: &[Ion<T>], atoms: &[usize]) -> Result<Vec<Ion<T>>, GeometryError> {
+    atoms.iter().map(|&index| {
+        mol.get(index).copied().ok_or(GeometryError::AtomIndexOutOfRange { index, natoms: mol.len() })
+    }).collect()
+}
+
+// The atoms of a followed by those of b, e.g. a dimer from two monomers;
+// atom n of b becomes atom a.len()+n
+pub fn merge<T: Float>(a: &[Ion<T>], b: &[Ion<T>]) -> Vec<Ion<T>> {
+    merge_translated(a, b, [T::zero();3])
+}
+
+// merge with b shifted by `offset` first
+pub fn merge_translated<T: Float>(a: &[Ion<T>], b: &[Ion<T>], offset: [T;3]) -> Vec<Ion<T>> {
+    let mut merged = a.to_vec();
+    merged.extend(b.iter().map(|ion| Ion { z_val: ion.z_val, x: ion.x+offset[0], y: ion.y+offset[1], z: ion.z+offset[2] }));
+    merged
+}
+
+// The angle i-vertex-k in radians, with `vertex` as the apex whatever
+// order the other two are given in
+pub fn angle_at<T: Float>(mol: &[Ion<T>], i: usize, vertex: usize, k: usize) -> Result<T, GeometryError> {
+    let atoms = select_atoms(mol, &[i, vertex, k])?;
+    Ok(Ion::bond_angle(&atoms[0], &atoms[1], &atoms[2]))
+}
+
+// Atoms are bonded when closer than BOND_TOLERANCE times the sum of their
+// covalent radii
+pub const BOND_TOLERANCE: f64 = 1.2;
+
+// Bonded pairs (j,i,length) with j<i, coordinates in angstrom
+pub fn bonds<T: Float>(mol: &[Ion<T>]) -> Result<Vec<(usize,usize,T)>, GeometryError> {
+    bonds_with(mol, BOND_TOLERANCE, &HashMap::new())
+}
+
+// bonds with `scale` in place of BOND_TOLERANCE, and covalent radii
+// (angstrom) from `radii` for the atomic numbers it contains, e.g. to
+// bond a metal more loosely than the tabulated radius allows. Large
+// molecules only test pairs in neighbouring cells of a cell list a little
+// wider than the longest possible bond, giving the same bonds in the same
+// order as testing every pair.
+pub fn bonds_with<T: Float>(mol: &[Ion<T>], scale: f64, radii: &HashMap<i32, f64>) -> Result<Vec<(usize,usize,T)>, GeometryError> {
+    let radii = covalent_radii(mol, radii)?;
+    let bonded = |j: usize, i: usize| -> Option<(usize,usize,T)> {
+        let cutoff = T::from_f64(scale*(radii[i]+radii[j]));
+        let l_sq = mol[i].bond_length_sq(&mol[j]);
+        if cutoff > T::zero() && l_sq < cutoff*cutoff {
+            Some((j,i,l_sq.sqrt()))
+        } else {
+            None
+        }
+    };
+
+    let longest = radii.iter().fold(0., |m: f64, &r| m.max(r));
+    let grid = if mol.len() >= GRID_MIN_ATOMS { CellGrid::new(mol, 1.01*2.*scale*longest) } else { None };
+    let mut bonds = Vec::new();
+    for (i, ion) in mol.iter().enumerate() {
+        match &grid {
+            Some(grid) => bonds.extend(grid.neighbours(ion).into_iter().filter(|&j| j < i).filter_map(|j| bonded(j, i))),
+            None => bonds.extend((0..i).filter_map(|j| bonded(j, i))),
+        }
+    }
+    Ok(bonds)
+}
+
+pub(crate) fn covalent_radii<T: Float>(mol: &[Ion<T>], overrides: &HashMap<i32, f64>) -> Result<Vec<f64>, GeometryError> {
+    mol.iter().map(|ion| {
+        overrides.get(&ion.z_val).copied()
+            .or_else(|| covalent_radius_for_z(ion.z_val))
+            .ok_or(GeometryError::UnknownElement(ion.z_val))
+    }).collect()
+}
+
+// Angle tuples are (vertex, a, b, angle) for the angle a-vertex-b, with the
+// two neighbours a<b.
+
+// One angle per atom triple k<j<i, taken at the middle-index atom j, so
+// stored as (j,k,i,angle). The loops only visit strictly ordered triples,
+// so each unordered triple appears exactly once, ordered by i, then j,
+// then k. See all_vertex_bond_angles for the other two vertex choices.
+pub fn bond_angles<T: Float>(mol: &[Ion<T>]) -> Result<Vec<(usize,usize,usize,T)>, GeometryError> {
+    let len = mol.len();
+    if len <= 2 {
+        Err(GeometryError::TooFewAtoms { needed: 3, found: len })
+    } else {
+        // exactly len choose 3 triples
+        let mut angles = Vec::with_capacity(len*(len-1)*(len-2)/6);
+        for (k, j, i) in triples(len) {
+            angles.push((j,k,i,Ion::bond_angle(&mol[i],&mol[j],&mol[k])));
+        }
+        Ok(angles)
+    }
+}
+
+// Counts of the angles from bond_angles (or any angle list) in bins of
+// `width`, in the same unit as the angles, as (bin start, count) from the
+// lowest occupied bin to the highest, empty bins in between included. A bin
+// holds angles from its start up to but not including start+width, except
+// that `top`, the largest angle possible (PI, or 180 for degrees), goes in
+// the last bin below it rather than opening a bin past the domain.
+pub fn angle_histogram(angles: &[(usize,usize,usize,f64)], width: f64, top: f64) -> Vec<(f64,usize)> {
+    // a little under top/width, so a width dividing top exactly in theory
+    // but not quite in floating point still ends on a whole bin
+    let last = ((top/width*(1.-1e-12)).ceil() as usize).max(1) - 1;
+    let bins: Vec<usize> = angles.iter().map(|&(_, _, _, theta)| ((theta/width).floor() as usize).min(last)).collect();
+    let (lo, hi) = match (bins.iter().min(), bins.iter().max()) {
+        (Some(&lo), Some(&hi)) => (lo, hi),
+        _ => return Vec::new(),
+    };
+    let mut counts = vec![0; hi-lo+1];
+    for bin in bins {
+        counts[bin-lo] += 1;
+    }
+    counts.into_iter().enumerate().map(|(n, count)| ((lo+n) as f64*width, count)).collect()
+}
+
+// Angles (j,a,b,angle) at every vertex j bonded to both a and b, with a<b,
+// ordered by vertex
+pub fn bonded_angles<T: Float>(mol: &[Ion<T>]) -> Result<Vec<(usize,usize,usize,T)>, GeometryError> {
+    let neighbours = neighbour_lists(mol)?;
+    let mut angles = Vec::new();
+    for (j, nbrs) in neighbours.iter().enumerate() {
+        for (n, &a) in nbrs.iter().enumerate() {
+            for &b in &nbrs[n+1..] {
+                angles.push((j,a,b,Ion::bond_angle(&mol[a],&mol[j],&mol[b])));
+            }
+        }
+    }
+    Ok(angles)
+}
+
+// Out-of-plane angles (i,j,k,l,angle) at every central atom k with three or
+// more bonded neighbours: the angle of the k-i bond out of the j-k-l plane,
+// as Ion::out_of_plane_angle, for each neighbour i and each pair j<l of the
+// others. Ordered by k, then i, j and l; planes that are undefined because
+// j-k-l is linear are left out.
+pub fn out_of_plane_angles<T: Float>(mol: &[Ion<T>]) -> Result<Vec<(usize,usize,usize,usize,T)>, GeometryError> {
+    let neighbours = neighbour_lists(mol)?;
+    let mut angles = Vec::new();
+    for (k, nbrs) in neighbours.iter().enumerate() {
+        if nbrs.len() < 3 {
+            continue;
+        }
+        for &i in nbrs {
+            let others: Vec<usize> = nbrs.iter().copied().filter(|&n| n != i).collect();
+            for (n, &j) in others.iter().enumerate() {
+                for &l in &others[n+1..] {
+                    if let Ok(theta) = Ion::out_of_plane_angle(&mol[i], &mol[j], &mol[k], &mol[l]) {
+                        angles.push((i,j,k,l,theta));
+                    }
+                }
+            }
+        }
+    }
+    Ok(angles)
+}
+
+// Bonded angles within this many radians (5 degrees) of 180 count as linear
+pub const LINEAR_ANGLE_TOL: f64 = 5.*PI/180.;
+
+// Bonded angles (j,a,b,angle) within `tol` radians of pi, in bonded_angles
+// order: linear arrangements such as CO2 or the central atom of an allene
+pub fn linear_angles<T: Float>(mol: &[Ion<T>], tol: T) -> Result<Vec<(usize,usize,usize,T)>, GeometryError> {
+    let pi = T::from_f64(PI);
+    Ok(bonded_angles(mol)?.into_iter().filter(|&(_, _, _, theta)| pi-theta <= tol).collect())
+}
+
+// Smallest and largest bonded angle at each atom that is the vertex of at
+// least one, as (vertex, min, max) in vertex order
+pub fn vertex_angle_ranges<T: Float>(mol: &[Ion<T>]) -> Result<Vec<(usize,T,T)>, GeometryError> {
+    let mut ranges: Vec<(usize,T,T)> = Vec::new();
+    // bonded_angles comes grouped by vertex
+    for (j, _, _, theta) in bonded_angles(mol)? {
+        match ranges.last_mut() {
+            Some(last) if last.0 == j => {
+                if theta < last.1 {
+                    last.1 = theta;
+                }
+                if theta > last.2 {
+                    last.2 = theta;
+                }
+            },
+            _ => ranges.push((j, theta, theta)),
+        }
+    }
+    Ok(ranges)
+}
+
+// VSEPR bond angle in radians for an atom with `coordination` bonded
+// neighbours and no lone pairs: linear, trigonal planar, tetrahedral or
+// octahedral. Five neighbours (90 and 120 degree angles) have no single
+// ideal and give None, as do fewer than two.
+pub fn ideal_angle(coordination: usize) -> Option<f64> {
+    match coordination {
+        2 => Some(PI),
+        3 => Some(2.*PI/3.),
+        4 => Some((-1f64/3.).acos()),
+        6 => Some(PI/2.),
+        _ => None,
+    }
+}
+
+// Bonded angles (j,a,b,angle,ideal) more than `tol` radians from the
+// ideal_angle for the vertex's coordination number, in bonded_angles order.
+// Lone pairs are not counted, so e.g. water's bent H-O-H is flagged against
+// the linear ideal for two neighbours.
+pub fn strained_angles<T: Float>(mol: &[Ion<T>], tol: T) -> Result<Vec<(usize,usize,usize,T,T)>, GeometryError> {
+    let coordination = coordination_numbers(mol)?;
+    Ok(bonded_angles(mol)?.into_iter()
+        .filter_map(|(j, a, b, theta)| {
+            let ideal = T::from_f64(ideal_angle(coordination[j])?);
+            if (theta-ideal).abs() > tol { Some((j, a, b, theta, ideal)) } else { None }
+        })
+        .collect())
+}
+
+// Number of bonded neighbours of each atom, by the same criterion as bonds
+pub fn coordination_numbers<T: Float>(mol: &[Ion<T>]) -> Result<Vec<usize>, GeometryError> {
+    Ok(neighbour_lists(mol)?.iter().map(|nbrs| nbrs.len()).collect())
+}
+
+// Each atom as (index, Z, bonded neighbours as (index, Z) in ascending index
+// order), by the same criterion as bonds
+pub fn adjacency_summary<T: Float>(mol: &[Ion<T>]) -> Result<Vec<(usize,i32,Vec<(usize,i32)>)>, GeometryError> {
+    Ok(neighbour_lists(mol)?.into_iter().enumerate()
+        .map(|(j, nbrs)| (j, mol[j].z_val, nbrs.into_iter().map(|i| (i, mol[i].z_val)).collect()))
+        .collect())
+}
+
+// Connected components of the bond graph, i.e. the separate molecules of a
+// cluster: each fragment's atoms in ascending order, fragments ordered by
+// their lowest atom
+pub fn molecular_fragments<T: Float>(mol: &[Ion<T>]) -> Result<Vec<Vec<usize>>, GeometryError> {
+    let neighbours = neighbour_lists(mol)?;
+    let mut seen = vec![false; mol.len()];
+    let mut fragments = Vec::new();
+    for start in 0..mol.len() {
+        if seen[start] {
+            continue;
+        }
+        seen[start] = true;
+        let mut fragment = vec![start];
+        let mut stack = vec![start];
+        while let Some(j) = stack.pop() {
+            for &i in &neighbours[j] {
+                if !seen[i] {
+                    seen[i] = true;
+                    fragment.push(i);
+                    stack.push(i);
+                }
+            }
+        }
+        fragment.sort_unstable();
+        fragments.push(fragment);
+    }
+    Ok(fragments)
+}
+
+// Pairs (i,j,distance), i<j, whose atoms lie in different fragments, in
+// bond_length_pairs order; the rest of the pairs are intramolecular
+pub fn intermolecular_distances<T: Float>(mol: &[Ion<T>]) -> Result<Vec<(usize,usize,T)>, GeometryError> {
+    let mut fragment_of = vec![0; mol.len()];
+    for (n, fragment) in molecular_fragments(mol)?.iter().enumerate() {
+        for &i in fragment {
+            fragment_of[i] = n;
+        }
+    }
+    Ok(bond_length_pairs(mol).filter(|&(i, j, _)| fragment_of[i] != fragment_of[j]).collect())
+}
+
+// Ascending indices of the atoms bonded to each atom
+fn neighbour_lists<T: Float>(mol: &[Ion<T>]) -> Result<Vec<Vec<usize>>, GeometryError> {
+    let mut neighbours = vec![Vec::new(); mol.len()];
+    for (j,i,_) in bonds(mol)? {
+        neighbours[i].push(j);
+        neighbours[j].push(i);
+    }
+    for nbrs in neighbours.iter_mut() {
+        nbrs.sort_unstable();
+    }
+    Ok(neighbours)
+}
+
+// Three angles per atom triple k<j<i, one for each atom as the vertex:
+// (j,k,i), (k,j,i) and (i,k,j).
+pub fn all_vertex_bond_angles<T: Float>(mol: &[Ion<T>]) -> Result<Vec<(usize,usize,usize,T)>, GeometryError> {
+    let len = mol.len();
+    if len <= 2 {
+        Err(GeometryError::TooFewAtoms { needed: 3, found: len })
+    } else {
+        // three vertices for each of the len choose 3 triples
+        let mut angles = Vec::with_capacity(len*(len-1)*(len-2)/2);
+        for (k, j, i) in triples(len) {
+            angles.push((j,k,i,Ion::bond_angle(&mol[i],&mol[j],&mol[k])));
+            angles.push((k,j,i,Ion::bond_angle(&mol[i],&mol[k],&mol[j])));
+            angles.push((i,k,j,Ion::bond_angle(&mol[k],&mol[i],&mol[j])));
+        }
+        Ok(angles)
+    }
+}
+
+// The dihedral angles of the molecule: bonded_dihedrals, with an error
+// rather than an empty list when there are too few atoms for any torsion
+pub fn dihedral_angles<T: Float>(mol: &[Ion<T>]) -> Result<Vec<(usize,usize,usize,usize,T)>, GeometryError> {
+    if mol.len() <= 3 {
+        Err(GeometryError::TooFewAtoms { needed: 4, found: mol.len() })
+    } else {
+        bonded_dihedrals(mol)
+    }
+}
+
+// Torsions (i,j,k,l,angle) along every bonded chain i-j-k-l, each central
+// bond counted once with j<k. Ordered by the central pair (j,k), then i and
+// l ascending, so the sequence depends only on the bond graph; chains
+// closing a three-membered ring (i == l) and undefined torsions are left out.
+pub fn bonded_dihedrals<T: Float>(mol: &[Ion<T>]) -> Result<Vec<(usize,usize,usize,usize,T)>, GeometryError> {
+    let neighbours = neighbour_lists(mol)?;
+    let mut central: Vec<(usize,usize)> = bonds(mol)?.iter().map(|&(j,k,_)| (j,k)).collect();
+    central.sort_unstable();
+
+    let mut dihedrals = Vec::new();
+    for (j, k) in central {
+        for &i in neighbours[j].iter().filter(|&&i| i != k) {
+            for &l in neighbours[k].iter().filter(|&&l| l != j && l != i) {
+                let phi = Ion::dihedral_angle(&mol[i],&mol[j],&mol[k],&mol[l]);
+                if !phi.is_nan() {
+                    dihedrals.push((i,j,k,l,phi));
+                }
+            }
+        }
+    }
+    Ok(dihedrals)
+}
+
+// Every index triple a<b<c below n, ordered by c, then b, then a: the
+// enumeration behind bond_angles, for custom per-triple analyses
+pub fn triples(n: usize) -> impl Iterator<Item=(usize,usize,usize)> {
+    (0..n).flat_map(|c| (0..c).flat_map(move |b| (0..b).map(move |a| (a,b,c))))
+}
+
+// Every index quadruple a<b<c<d below n, ordered by d, then c, b and a, for
+// custom per-quadruple analyses
+pub fn quadruples(n: usize) -> impl Iterator<Item=(usize,usize,usize,usize)> {
+    (0..n).flat_map(|d| triples(d).map(move |(a,b,c)| (a,b,c,d)))
+}
+
+// Closer than any real bond (H2 is 0.74 A); used by the CLI's overlap warning
+pub const OVERLAP_TOL: f64 = 0.5;
+
+// Pairs (j,i,distance) with j<i closer than `tol`. A diagnostic rather than
+// an error, since some inputs place atoms close on purpose.
+pub fn find_overlaps<T: Float>(mol: &[Ion<T>], tol: T) -> Vec<(usize,usize,T)> {
+    // a negative or non-finite tolerance finds no overlaps
+    contacts_within(mol, tol).unwrap_or_default()
+}
+
+// Every pair (j,i,distance) with j<i closer than `cutoff`, whatever the
+// elements, in bonds order: the close contacts behind a contact map.
+// Large molecules go through a cell list, with the same result. A negative
+// or non-finite cutoff is an InvalidCutoff error.
+pub fn contacts_within<T: Float>(mol: &[Ion<T>], cutoff: T) -> Result<Vec<(usize,usize,T)>, GeometryError> {
+    if cutoff < T::zero() || !cutoff.is_finite() {
+        return Err(GeometryError::InvalidCutoff(cutoff.to_f64()));
+    }
+    let close = |j: usize, i: usize| -> Option<(usize,usize,T)> {
+        let l_sq = mol[i].bond_length_sq(&mol[j]);
+        if l_sq < cutoff*cutoff {
+            Some((j,i,l_sq.sqrt()))
+        } else {
+            None
+        }
+    };
+
+    let grid = if mol.len() >= GRID_MIN_ATOMS { CellGrid::new(mol, 1.01*cutoff.to_f64()) } else { None };
+    let mut contacts = Vec::new();
+    for (i, ion) in mol.iter().enumerate() {
+        match &grid {
+            Some(grid) => contacts.extend(grid.neighbours(ion).into_iter().filter(|&j| j < i).filter_map(|j| close(j, i))),
+            None => contacts.extend((0..i).filter_map(|j| close(j, i))),
+        }
+    }
+    Ok(contacts)
+}
+
+// Reject geometries that would poison every downstream calculation:
+// non-finite coordinates, and atoms sharing a position (zero bond lengths
+// divide by zero in the angle functions)
+pub fn validate_molecule<T: Float>(mol: &[Ion<T>]) -> Result<(), GeometryError> {
+    for (i, ion) in mol.iter().enumerate() {
+        for &(axis, value) in &[('x', ion.x), ('y', ion.y), ('z', ion.z)] {
+            if !value.is_finite() {
+                return Err(GeometryError::NonFiniteCoordinate { index: i, axis, value: value.to_f64() });
+            }
+        }
+    }
+
+    // Coincident atoms always share a cube, so for large molecules only the
+    // neighbouring cubes need checking; both paths report the same first pair
+    let grid = if mol.len() >= GRID_MIN_ATOMS { CellGrid::for_nearest(mol) } else { None };
+    for i in 0..mol.len() {
+        let candidates: Vec<usize> = match &grid {
+            Some(grid) => grid.neighbours(&mol[i]).into_iter().take_while(|&j| j < i).collect(),
+            None => (0..i).collect(),
+        };
+        for j in candidates {
+            if mol[i].bond_length(&mol[j]) == T::zero() {
+                return Err(GeometryError::CoincidentAtoms(j, i));
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+
+use crate::float::Float;
+use crate::geometry::Ion;
+
+// Below this many atoms the plain O(N^2) pair loops are faster than a grid
+pub(crate) const GRID_MIN_ATOMS: usize = 500;
+
+// Cube side (angstrom) for nearest-neighbour searches, about the length
+// of a long covalent bond
+const NEAREST_CELL: f64 = 2.5;
+
+// Cell list: atoms binned into cubes of side `cell`, so every atom less
+// than `cell` from another lies in the same cube or one of the 26 around
+// it. Only occupied cubes are stored.
+pub(crate) struct CellGrid {
+    cell: f64,
+    cubes: HashMap<[i64;3], Vec<usize>>,
+}
+
+impl CellGrid {
+
+    // None unless `cell` is a usable (positive, finite) cube side and every
+    // coordinate is finite, leaving such inputs to the brute-force loops
+    pub(crate) fn new<T: Float>(mol: &[Ion<T>], cell: f64) -> Option<CellGrid> {
+        if cell <= 0. || !cell.is_finite() {
+            return None;
+        }
+        if !mol.iter().all(|ion| ion.x.is_finite() && ion.y.is_finite() && ion.z.is_finite()) {
+            return None;
+        }
+        let mut grid = CellGrid { cell, cubes: HashMap::new() };
+        for (i, ion) in mol.iter().enumerate() {
+            let key = grid.key(ion);
+            grid.cubes.entry(key).or_default().push(i);
+        }
+        Some(grid)
+    }
+
+    // Cubes for a nearest-neighbour search, sized for bonded distances:
+    // molecules have bounded density, so a few shells usually suffice
+    pub(crate) fn for_nearest<T: Float>(mol: &[Ion<T>]) -> Option<CellGrid> {
+        CellGrid::new(mol, NEAREST_CELL)
+    }
+
+    fn key<T: Float>(&self, ion: &Ion<T>) -> [i64;3] {
+        [ion.x, ion.y, ion.z].map(|c| (c.to_f64()/self.cell).floor() as i64)
+    }
+
+    // Atoms in the cubes exactly `reach` cubes (Chebyshev distance) from
+    // the one holding `ion`, in no particular order
+    fn shell<T: Float>(&self, ion: &Ion<T>, reach: i64) -> Vec<usize> {
+        let [cx, cy, cz] = self.key(ion);
+        let mut atoms = Vec::new();
+        for dx in -reach..=reach {
+            for dy in -reach..=reach {
+                for dz in -reach..=reach {
+                    if dx.abs().max(dy.abs()).max(dz.abs()) != reach {
+                        continue;
+                    }
+                    let key = [cx.saturating_add(dx), cy.saturating_add(dy), cz.saturating_add(dz)];
+                    if let Some(cube) = self.cubes.get(&key) {
+                        atoms.extend(cube);
+                    }
+                }
+            }
+        }
+        atoms
+    }
+
+    // Atoms in the 27 cubes around `ion`, ascending
+    pub(crate) fn neighbours<T: Float>(&self, ion: &Ion<T>) -> Vec<usize> {
+        let mut atoms = self.shell(ion, 0);
+        atoms.extend(self.shell(ion, 1));
+        atoms.sort_unstable();
+        atoms
+    }
+
+    // The atom closest to mol[a] as (index, distance), ties going to the
+    // lower index, searching outward shell by shell. Once shells 0 to
+    // `reach` are done every other atom is at least reach*cell away, so the
+    // search stops when the best distance is under (reach-1)*cell, a shell
+    // to spare for rounding. For a far outlier, whose search would cover
+    // more cubes than the molecule has atoms, it scans every atom instead.
+    pub(crate) fn nearest<T: Float>(&self, mol: &[Ion<T>], a: usize) -> Option<(usize,T)> {
+        let mut best: Option<(usize,T)> = None;
+        let consider = |b: usize, best: &mut Option<(usize,T)>| {
+            if b == a {
+                return;
+            }
+            let l = mol[a].bond_length(&mol[b]);
+            if best.is_none_or(|(n, d)| l < d || (l <= d && b < n)) {
+                *best = Some((b, l));
+            }
+        };
+        let mut reach = 0;
+        loop {
+            if ((2*reach+1) as usize).pow(3) > mol.len() {
+                for b in 0..mol.len() {
+                    consider(b, &mut best);
+                }
+                return best;
+            }
+            for b in self.shell(&mol[a], reach) {
+                consider(b, &mut best);
+            }
+            if let Some((_, d)) = best {
+                if d.to_f64() < (reach-1) as f64*self.cell {
+                    return best;
+                }
+            }
+            reach += 1;
+        }
+    }
+
+}
@@ -0,0 +1,295 @@
+use std::io;
+
+use crate::io::is_gzip;
+
+// Minimal gzip (RFC 1952) and DEFLATE (RFC 1951) decoder, enough to read
+// compressed geometry files without an external crate. Concatenated gzip
+// members are decoded one after another, as gunzip does.
+
+pub fn decompress(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        pos = member(data, pos, &mut out)?;
+    }
+    Ok(out)
+}
+
+fn corrupt(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("corrupt gzip input: {}", what))
+}
+
+// Decode the member starting at data[pos], returning the offset after it
+fn member(data: &[u8], pos: usize, out: &mut Vec<u8>) -> io::Result<usize> {
+    const FHCRC: u8 = 0x02;
+    const FEXTRA: u8 = 0x04;
+    const FNAME: u8 = 0x08;
+    const FCOMMENT: u8 = 0x10;
+
+    let header = data.get(pos..pos+10).ok_or_else(|| corrupt("truncated header"))?;
+    if !is_gzip(header) {
+        return Err(corrupt("bad magic number"));
+    }
+    if header[2] != 8 {
+        return Err(corrupt("unknown compression method"));
+    }
+    let flags = header[3];
+    let mut p = pos+10;
+    if flags & FEXTRA != 0 {
+        let len = data.get(p..p+2).ok_or_else(|| corrupt("truncated header"))?;
+        p += 2 + (len[0] as usize | (len[1] as usize) << 8);
+    }
+    for flag in [FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            let len = data.get(p..).and_then(|rest| rest.iter().position(|&b| b == 0))
+                .ok_or_else(|| corrupt("truncated header"))?;
+            p += len+1;
+        }
+    }
+    if flags & FHCRC != 0 {
+        p += 2;
+    }
+    if p > data.len() {
+        return Err(corrupt("truncated header"));
+    }
+
+    let start = out.len();
+    let mut bits = BitReader { data, pos: p, bit_buf: 0, bit_count: 0 };
+    inflate(&mut bits, out)?;
+
+    let trailer = data.get(bits.pos..bits.pos+8).ok_or_else(|| corrupt("truncated trailer"))?;
+    let word = |b: &[u8]| u32::from_le_bytes([b[0], b[1], b[2], b[3]]);
+    if word(&trailer[0..4]) != crc32(&out[start..]) {
+        return Err(corrupt("checksum mismatch"));
+    }
+    if word(&trailer[4..8]) != (out.len()-start) as u32 {
+        return Err(corrupt("length mismatch"));
+    }
+    Ok(bits.pos+8)
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bit_buf: u32,
+    bit_count: u32,
+}
+
+impl<'a> BitReader<'a> {
+
+    // Next n bits, least significant first
+    fn bits(&mut self, n: u32) -> io::Result<u32> {
+        while self.bit_count < n {
+            let byte = *self.data.get(self.pos).ok_or_else(|| corrupt("unexpected end of data"))?;
+            self.pos += 1;
+            self.bit_buf |= (byte as u32) << self.bit_count;
+            self.bit_count += 8;
+        }
+        let value = self.bit_buf & ((1u64 << n) - 1) as u32;
+        self.bit_buf = if n == 32 { 0 } else { self.bit_buf >> n };
+        self.bit_count -= n;
+        Ok(value)
+    }
+
+    // Drop the rest of the current byte
+    fn align(&mut self) {
+        self.bit_buf = 0;
+        self.bit_count = 0;
+    }
+
+}
+
+// Canonical Huffman code as code-length counts and symbols in code order
+struct Huffman {
+    counts: [u16;16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+
+    fn new(lengths: &[u8]) -> Huffman {
+        let mut counts = [0u16;16];
+        for &l in lengths {
+            counts[l as usize] += 1;
+        }
+        counts[0] = 0;
+
+        let mut offsets = [0u16;16];
+        for n in 1..16 {
+            offsets[n] = offsets[n-1] + counts[n-1];
+        }
+        let mut symbols = vec![0u16; lengths.len()];
+        for (sym, &l) in lengths.iter().enumerate() {
+            if l != 0 {
+                symbols[offsets[l as usize] as usize] = sym as u16;
+                offsets[l as usize] += 1;
+            }
+        }
+        Huffman { counts, symbols }
+    }
+
+    fn decode(&self, bits: &mut BitReader) -> io::Result<u16> {
+        let mut code = 0i32;
+        let mut first = 0i32;
+        let mut index = 0i32;
+        for len in 1..16 {
+            code |= bits.bits(1)? as i32;
+            let count = self.counts[len] as i32;
+            if code - count < first {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first += count;
+            first <<= 1;
+            code <<= 1;
+        }
+        Err(corrupt("invalid Huffman code"))
+    }
+
+}
+
+const LENGTH_BASE: [u16;29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31,
+    35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8;29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2,
+    3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DIST_BASE: [u16;30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193,
+    257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8;30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6,
+    7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13,
+];
+
+fn inflate(bits: &mut BitReader, out: &mut Vec<u8>) -> io::Result<()> {
+    let start = out.len();
+    loop {
+        let last = bits.bits(1)? == 1;
+        match bits.bits(2)? {
+            0 => stored_block(bits, out)?,
+            1 => {
+                let mut lengths = [0u8;288+30];
+                lengths[..144].fill(8);
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                lengths[280..288].fill(8);
+                lengths[288..].fill(5);
+                let lit = Huffman::new(&lengths[..288]);
+                let dist = Huffman::new(&lengths[288..]);
+                compressed_block(bits, out, start, &lit, &dist)?;
+            },
+            2 => {
+                let (lit, dist) = dynamic_codes(bits)?;
+                compressed_block(bits, out, start, &lit, &dist)?;
+            },
+            _ => return Err(corrupt("invalid block type")),
+        }
+        if last {
+            bits.align();
+            return Ok(());
+        }
+    }
+}
+
+fn stored_block(bits: &mut BitReader, out: &mut Vec<u8>) -> io::Result<()> {
+    bits.align();
+    let header = bits.data.get(bits.pos..bits.pos+4).ok_or_else(|| corrupt("truncated block"))?;
+    let len = u16::from_le_bytes([header[0], header[1]]);
+    let nlen = u16::from_le_bytes([header[2], header[3]]);
+    if len != !nlen {
+        return Err(corrupt("stored block length check failed"));
+    }
+    let begin = bits.pos+4;
+    let block = bits.data.get(begin..begin+len as usize).ok_or_else(|| corrupt("truncated block"))?;
+    out.extend_from_slice(block);
+    bits.pos = begin+len as usize;
+    Ok(())
+}
+
+fn dynamic_codes(bits: &mut BitReader) -> io::Result<(Huffman, Huffman)> {
+    const ORDER: [usize;19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+    let nlen = bits.bits(5)? as usize + 257;
+    let ndist = bits.bits(5)? as usize + 1;
+    let ncode = bits.bits(4)? as usize + 4;
+    if nlen > 286 || ndist > 30 {
+        return Err(corrupt("too many codes"));
+    }
+
+    let mut code_lengths = [0u8;19];
+    for &i in &ORDER[..ncode] {
+        code_lengths[i] = bits.bits(3)? as u8;
+    }
+    let code = Huffman::new(&code_lengths);
+
+    let mut lengths = vec![0u8; nlen+ndist];
+    let mut n = 0;
+    while n < nlen+ndist {
+        let sym = code.decode(bits)?;
+        let (value, repeat) = match sym {
+            0..=15 => (sym as u8, 1),
+            16 => {
+                let prev = *lengths.get(n.wrapping_sub(1)).ok_or_else(|| corrupt("repeat with no previous length"))?;
+                (prev, 3 + bits.bits(2)? as usize)
+            },
+            17 => (0, 3 + bits.bits(3)? as usize),
+            _ => (0, 11 + bits.bits(7)? as usize),
+        };
+        if n+repeat > lengths.len() {
+            return Err(corrupt("too many code lengths"));
+        }
+        lengths[n..n+repeat].fill(value);
+        n += repeat;
+    }
+    if lengths[256] == 0 {
+        return Err(corrupt("no end-of-block code"));
+    }
+
+    Ok((Huffman::new(&lengths[..nlen]), Huffman::new(&lengths[nlen..])))
+}
+
+// Literals and back-references until end of block. `start` is where this
+// member's output begins, the limit for back-references.
+fn compressed_block(bits: &mut BitReader, out: &mut Vec<u8>, start: usize, lit: &Huffman, dist: &Huffman) -> io::Result<()> {
+    loop {
+        let sym = lit.decode(bits)? as usize;
+        if sym < 256 {
+            out.push(sym as u8);
+        } else if sym == 256 {
+            return Ok(());
+        } else {
+            let sym = sym-257;
+            if sym >= LENGTH_BASE.len() {
+                return Err(corrupt("invalid length code"));
+            }
+            let len = LENGTH_BASE[sym] as usize + bits.bits(LENGTH_EXTRA[sym] as u32)? as usize;
+            let dsym = dist.decode(bits)? as usize;
+            if dsym >= DIST_BASE.len() {
+                return Err(corrupt("invalid distance code"));
+            }
+            let d = DIST_BASE[dsym] as usize + bits.bits(DIST_EXTRA[dsym] as u32)? as usize;
+            if d > out.len()-start {
+                return Err(corrupt("distance too far back"));
+            }
+            // byte by byte, since the copy may overlap its own output
+            for _ in 0..len {
+                out.push(out[out.len()-d]);
+            }
+        }
+    }
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb88320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
@@ -0,0 +1,271 @@
+use std::fmt;
+
+use crate::align::mean_position;
+use crate::elements::{isotope_mass, mass_for_z};
+use crate::error::GeometryError;
+use crate::float::Float;
+use crate::geometry::Ion;
+use crate::linalg::eig_sym3;
+
+// Coordinates may be f32 or f64, but everything here is mass-weighted
+// against the f64 mass tables, so it is accumulated and returned in f64.
+
+// Standard atomic weights of each atom
+pub fn standard_masses<T: Float>(mol: &[Ion<T>]) -> Result<Vec<f64>, GeometryError> {
+    mol.iter()
+        .map(|ion| mass_for_z(ion.z_val).ok_or(GeometryError::UnknownElement(ion.z_val)))
+        .collect()
+}
+
+// Per-atom masses where isotopes[i] is atom i's mass number, or None for
+// the standard atomic weight. Feed the result to the *_with_masses
+// functions for isotopologues such as D2O.
+pub fn isotope_masses<T: Float>(mol: &[Ion<T>], isotopes: &[Option<u32>]) -> Result<Vec<f64>, GeometryError> {
+    if isotopes.len() != mol.len() {
+        return Err(GeometryError::AtomCountMismatch { declared: mol.len(), found: isotopes.len() });
+    }
+    mol.iter().zip(isotopes).map(|(ion, isotope)| match *isotope {
+        None => mass_for_z(ion.z_val).ok_or(GeometryError::UnknownElement(ion.z_val)),
+        Some(a) => isotope_mass(ion.z_val, a)
+            .ok_or(GeometryError::UnknownIsotope { z: ion.z_val, mass_number: a }),
+    }).collect()
+}
+
+fn check_masses<T: Float>(mol: &[Ion<T>], masses: &[f64]) -> Result<(), GeometryError> {
+    if mol.is_empty() {
+        return Err(GeometryError::TooFewAtoms { needed: 1, found: 0 });
+    }
+    if masses.len() != mol.len() {
+        return Err(GeometryError::AtomCountMismatch { declared: mol.len(), found: masses.len() });
+    }
+    Ok(())
+}
+
+pub fn center_of_mass<T: Float>(mol: &[Ion<T>]) -> Result<(f64,f64,f64), GeometryError> {
+    center_of_mass_with_masses(mol, &standard_masses(mol)?)
+}
+
+pub fn center_of_mass_with_masses<T: Float>(mol: &[Ion<T>], masses: &[f64]) -> Result<(f64,f64,f64), GeometryError> {
+    check_masses(mol, masses)?;
+
+    let mut total = 0.;
+    let mut com = (0.,0.,0.);
+    for (ion, &m) in mol.iter().zip(masses) {
+        total += m;
+        com.0 += m*ion.x.to_f64();
+        com.1 += m*ion.y.to_f64();
+        com.2 += m*ion.z.to_f64();
+    }
+
+    Ok((com.0/total, com.1/total, com.2/total))
+}
+
+// Unweighted mean of the positions. Needs no masses, so any element will
+// do; its offset from the center of mass shows how the mass is distributed.
+pub fn centroid<T: Float>(mol: &[Ion<T>]) -> Result<(f64,f64,f64), GeometryError> {
+    if mol.is_empty() {
+        return Err(GeometryError::TooFewAtoms { needed: 1, found: 0 });
+    }
+    Ok(mean_position(mol))
+}
+
+pub fn translate_to_com<T: Float>(mol: &mut [Ion<T>]) -> Result<(), GeometryError> {
+    let com = center_of_mass(mol)?;
+    for ion in mol.iter_mut() {
+        ion.x = T::from_f64(ion.x.to_f64()-com.0);
+        ion.y = T::from_f64(ion.y.to_f64()-com.1);
+        ion.z = T::from_f64(ion.z.to_f64()-com.2);
+    }
+    Ok(())
+}
+
+// Mass-weighted RMS distance of the atoms from the center of mass
+pub fn radius_of_gyration<T: Float>(mol: &[Ion<T>]) -> Result<f64, GeometryError> {
+    let com = center_of_mass(mol)?;
+    let mut total = 0.;
+    let mut sum_sq = 0.;
+    for ion in mol {
+        // masses were already checked by center_of_mass
+        let m = mass_for_z(ion.z_val).unwrap();
+        total += m;
+        sum_sq += m*((ion.x.to_f64()-com.0).powi(2) + (ion.y.to_f64()-com.1).powi(2) + (ion.z.to_f64()-com.2).powi(2));
+    }
+    Ok((sum_sq/total).sqrt())
+}
+
+// Mass-weighted inertia tensor about the center of mass, in amu times the
+// square of the input length unit
+pub fn inertia_tensor<T: Float>(mol: &[Ion<T>]) -> Result<[[f64;3];3], GeometryError> {
+    inertia_tensor_with_masses(mol, &standard_masses(mol)?)
+}
+
+pub fn inertia_tensor_with_masses<T: Float>(mol: &[Ion<T>], masses: &[f64]) -> Result<[[f64;3];3], GeometryError> {
+    let com = center_of_mass_with_masses(mol, masses)?;
+    inertia_tensor_about_with_masses(mol, masses, [com.0, com.1, com.2])
+}
+
+// Inertia tensor about an arbitrary point, e.g. an atom on a rotation axis.
+// By the parallel-axis theorem it exceeds the tensor about the center of
+// mass by M(|d|^2 I - d d^T), d being the offset of the COM from `origin`.
+pub fn inertia_tensor_about<T: Float>(mol: &[Ion<T>], origin: [f64;3]) -> Result<[[f64;3];3], GeometryError> {
+    inertia_tensor_about_with_masses(mol, &standard_masses(mol)?, origin)
+}
+
+pub fn inertia_tensor_about_with_masses<T: Float>(mol: &[Ion<T>], masses: &[f64], origin: [f64;3]) -> Result<[[f64;3];3], GeometryError> {
+    check_masses(mol, masses)?;
+
+    let mut tensor = [[0.;3];3];
+    for (ion, &m) in mol.iter().zip(masses) {
+        let r = [ion.x.to_f64()-origin[0], ion.y.to_f64()-origin[1], ion.z.to_f64()-origin[2]];
+        let r_sq = r[0]*r[0] + r[1]*r[1] + r[2]*r[2];
+        for a in 0..3 {
+            for b in 0..3 {
+                let delta = if a == b { r_sq } else { 0. };
+                tensor[a][b] += m*(delta - r[a]*r[b]);
+            }
+        }
+    }
+
+    Ok(tensor)
+}
+
+// Principal moments Ia <= Ib <= Ic
+pub fn principal_moments<T: Float>(mol: &[Ion<T>]) -> Result<[f64;3], GeometryError> {
+    principal_moments_with_masses(mol, &standard_masses(mol)?)
+}
+
+pub fn principal_moments_with_masses<T: Float>(mol: &[Ion<T>], masses: &[f64]) -> Result<[f64;3], GeometryError> {
+    Ok(eig_sym3(inertia_tensor_with_masses(mol, masses)?).0)
+}
+
+// Standard orientation: move the center of mass to the origin and rotate
+// onto the principal axes, with Ia, Ib and Ic along x, y and z. An axis
+// is only defined up to sign, so x and y point the way the (mass-weighted)
+// third moment of the coordinates is positive, falling back to the sign
+// that makes the axis' largest component positive for symmetric molecules;
+// z = x cross y keeps the transformation a proper rotation.
+pub fn to_principal_axes<T: Float>(mol: &mut [Ion<T>]) -> Result<(), GeometryError> {
+    translate_to_com(mol)?;
+    let (_, vectors) = eig_sym3(inertia_tensor(mol)?);
+    let mut axes = [[0.;3];3];
+    for (n, axis) in axes.iter_mut().enumerate() {
+        *axis = [vectors[0][n], vectors[1][n], vectors[2][n]];
+    }
+
+    let project = |axis: &[f64;3], ion: &Ion<T>| axis[0]*ion.x.to_f64() + axis[1]*ion.y.to_f64() + axis[2]*ion.z.to_f64();
+    for axis in axes.iter_mut().take(2) {
+        let skew: f64 = mol.iter()
+            .map(|ion| mass_for_z(ion.z_val).unwrap()*project(axis, ion).powi(3))
+            .sum();
+        let scale: f64 = mol.iter()
+            .map(|ion| mass_for_z(ion.z_val).unwrap()*project(axis, ion).abs().powi(3))
+            .sum();
+        let flip = if skew.abs() > 1e-8*scale {
+            skew < 0.
+        } else {
+            let dominant = axis.iter().fold(0., |d: f64, &c| if c.abs() > d.abs() { c } else { d });
+            dominant < 0.
+        };
+        if flip {
+            *axis = axis.map(|c| -c);
+        }
+    }
+    let [a, b, _] = axes;
+    axes[2] = [a[1]*b[2]-a[2]*b[1], a[2]*b[0]-a[0]*b[2], a[0]*b[1]-a[1]*b[0]];
+
+    for ion in mol.iter_mut() {
+        let (x, y, z) = (project(&axes[0], ion), project(&axes[1], ion), project(&axes[2], ion));
+        ion.x = T::from_f64(x);
+        ion.y = T::from_f64(y);
+        ion.z = T::from_f64(z);
+    }
+    Ok(())
+}
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum RotorType {
+    Linear,
+    SphericalTop,
+    ProlateSymmetricTop,
+    OblateSymmetricTop,
+    AsymmetricTop,
+}
+
+impl fmt::Display for RotorType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(match self {
+            RotorType::Linear => "linear",
+            RotorType::SphericalTop => "spherical top",
+            RotorType::ProlateSymmetricTop => "prolate symmetric top",
+            RotorType::OblateSymmetricTop => "oblate symmetric top",
+            RotorType::AsymmetricTop => "asymmetric top",
+        })
+    }
+}
+
+// Moments closer than ROTOR_TOL times the largest moment count as equal
+pub const ROTOR_TOL: f64 = 1e-4;
+
+pub fn rotor_type<T: Float>(mol: &[Ion<T>]) -> Result<RotorType, GeometryError> {
+    rotor_type_with_tol(mol, ROTOR_TOL)
+}
+
+pub fn rotor_type_with_tol<T: Float>(mol: &[Ion<T>], tol: f64) -> Result<RotorType, GeometryError> {
+    let [ia, ib, ic] = principal_moments(mol)?;
+    let eq = |a: f64, b: f64| (a-b).abs() <= tol*ic;
+
+    Ok(if eq(ia, ic) {
+        // also covers a lone atom, where every moment is zero
+        RotorType::SphericalTop
+    } else if eq(ia, 0.) {
+        RotorType::Linear
+    } else if eq(ia, ib) {
+        RotorType::OblateSymmetricTop
+    } else if eq(ib, ic) {
+        RotorType::ProlateSymmetricTop
+    } else {
+        RotorType::AsymmetricTop
+    })
+}
+
+// CODATA 2018 values, SI units
+const PLANCK: f64 = 6.62607015e-34;
+const SPEED_OF_LIGHT: f64 = 2.99792458e8;
+const AMU_KG: f64 = 1.66053906660e-27;
+const ANGSTROM_M: f64 = 1e-10;
+
+// Rotational constants A >= B >= C in cm^-1, from B = h/(8 pi^2 c I).
+// Coordinates are taken to be in angstrom, so the moments are in amu A^2.
+// A component whose moment vanishes (A for a linear molecule, all three
+// for a lone atom) has no finite constant and is None.
+pub fn rotational_constants<T: Float>(mol: &[Ion<T>]) -> Result<[Option<f64>;3], GeometryError> {
+    rotational_constants_with_masses(mol, &standard_masses(mol)?)
+}
+
+pub fn rotational_constants_with_masses<T: Float>(mol: &[Ion<T>], masses: &[f64]) -> Result<[Option<f64>;3], GeometryError> {
+    let hz = rotational_constants_hz(principal_moments_with_masses(mol, masses)?);
+    // c in cm/s
+    Ok(hz.map(|b| b.map(|b| b/(SPEED_OF_LIGHT*100.))))
+}
+
+// The same constants in MHz, from B = h/(8 pi^2 I)
+pub fn rotational_constants_mhz<T: Float>(mol: &[Ion<T>]) -> Result<[Option<f64>;3], GeometryError> {
+    rotational_constants_mhz_with_masses(mol, &standard_masses(mol)?)
+}
+
+pub fn rotational_constants_mhz_with_masses<T: Float>(mol: &[Ion<T>], masses: &[f64]) -> Result<[Option<f64>;3], GeometryError> {
+    let hz = rotational_constants_hz(principal_moments_with_masses(mol, masses)?);
+    Ok(hz.map(|b| b.map(|b| b*1e-6)))
+}
+
+fn rotational_constants_hz(moments: [f64;3]) -> [Option<f64>;3] {
+    let ic = moments[2];
+    moments.map(|i| {
+        if i <= ROTOR_TOL*ic {
+            None
+        } else {
+            let i_si = i*AMU_KG*ANGSTROM_M*ANGSTROM_M;
+            Some(PLANCK/(8.*std::f64::consts::PI.powi(2)*i_si))
+        }
+    })
+}
@@ -0,0 +1,141 @@
+use crate::error::GeometryError;
+use crate::geometry::Ion;
+use crate::linalg::{cross, dot};
+
+// An internal coordinate given by atom indices. Angles are a-vertex-b and
+// dihedrals i-j-k-l, matching Ion::bond_angle and Ion::dihedral_angle.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum InternalCoordinate {
+    Bond(usize, usize),
+    Angle(usize, usize, usize),
+    Dihedral(usize, usize, usize, usize),
+}
+
+// sin(theta) below which an angle is treated as linear
+const LINEAR_TOL: f64 = 1e-8;
+
+impl InternalCoordinate {
+
+    fn atoms(&self) -> Vec<usize> {
+        match *self {
+            InternalCoordinate::Bond(i, j) => vec![i, j],
+            InternalCoordinate::Angle(a, j, b) => vec![a, j, b],
+            InternalCoordinate::Dihedral(i, j, k, l) => vec![i, j, k, l],
+        }
+    }
+
+    // Bond lengths in angstrom, angles in radians
+    pub fn value(&self, mol: &[Ion]) -> Result<f64, GeometryError> {
+        check_indices(&self.atoms(), mol.len())?;
+        Ok(match *self {
+            InternalCoordinate::Bond(i, j) => mol[i].bond_length(&mol[j]),
+            InternalCoordinate::Angle(a, j, b) => Ion::bond_angle(&mol[a], &mol[j], &mol[b]),
+            InternalCoordinate::Dihedral(i, j, k, l) =>
+                Ion::dihedral_angle(&mol[i], &mol[j], &mol[k], &mol[l]),
+        })
+    }
+
+    // Analytic derivative with respect to the coordinates of each atom in
+    // atoms() order
+    fn gradient(&self, mol: &[Ion]) -> Result<Vec<[f64;3]>, GeometryError> {
+        check_indices(&self.atoms(), mol.len())?;
+        match *self {
+            InternalCoordinate::Bond(i, j) => {
+                let (gi, gj) = mol[i].bond_length_gradient(&mol[j]);
+                Ok(vec![gi, gj])
+            },
+            InternalCoordinate::Angle(a, j, b) => {
+                let (ga, gj, gb) = Ion::bond_angle_gradient(&mol[a], &mol[j], &mol[b])
+                    .map_err(|_| GeometryError::LinearAngle(a, j, b))?;
+                Ok(vec![ga, gj, gb])
+            },
+            InternalCoordinate::Dihedral(i, j, k, l) => dihedral_gradient(mol, i, j, k, l),
+        }
+    }
+
+}
+
+fn check_indices(atoms: &[usize], natoms: usize) -> Result<(), GeometryError> {
+    match atoms.iter().find(|&&i| i >= natoms) {
+        Some(&index) => Err(GeometryError::AtomIndexOutOfRange { index, natoms }),
+        None => Ok(()),
+    }
+}
+
+// Torsion derivatives in the Blondel-Karplus form, which avoids the
+// singularities of Wilson's original expressions away from linear angles
+fn dihedral_gradient(mol: &[Ion], i: usize, j: usize, k: usize, l: usize) -> Result<Vec<[f64;3]>, GeometryError> {
+    let f = mol[j].displacement(&mol[i]);
+    let g = mol[k].displacement(&mol[j]);
+    let h = mol[k].displacement(&mol[l]);
+    let a = cross(f, g);
+    let b = cross(h, g);
+    let aa = dot(a, a);
+    let bb = dot(b, b);
+    let l_g = dot(g, g).sqrt();
+    if aa.sqrt() <= LINEAR_TOL*dot(f, f).sqrt()*l_g {
+        return Err(GeometryError::LinearAngle(i, j, k));
+    }
+    if bb.sqrt() <= LINEAR_TOL*dot(h, h).sqrt()*l_g {
+        return Err(GeometryError::LinearAngle(j, k, l));
+    }
+
+    let fg = dot(f, g)/(aa*l_g);
+    let hg = dot(h, g)/(bb*l_g);
+    let mut gi = [0.;3];
+    let mut gj = [0.;3];
+    let mut gk = [0.;3];
+    let mut gl = [0.;3];
+    for c in 0..3 {
+        gi[c] = -l_g/aa*a[c];
+        gl[c] = l_g/bb*b[c];
+        gj[c] = -gi[c] + fg*a[c] - hg*b[c];
+        gk[c] = -gl[c] - fg*a[c] + hg*b[c];
+    }
+    Ok(vec![gi, gj, gk, gl])
+}
+
+// Wilson B-matrix: one row per internal coordinate, one column per
+// Cartesian coordinate (x0, y0, z0, x1, ...), so it is M x 3N
+pub fn wilson_b_matrix(mol: &[Ion], coords: &[InternalCoordinate]) -> Result<Vec<Vec<f64>>, GeometryError> {
+    let mut b = vec![vec![0.;3*mol.len()];coords.len()];
+    for (row, coord) in b.iter_mut().zip(coords) {
+        for (atom, grad) in coord.atoms().into_iter().zip(coord.gradient(mol)?) {
+            for c in 0..3 {
+                row[3*atom+c] += grad[c];
+            }
+        }
+    }
+    Ok(b)
+}
+
+// Central finite-difference B-matrix with displacement `step` (angstrom),
+// for checking wilson_b_matrix. Torsion differences are wrapped into
+// (-pi, pi] so a dihedral near +-pi does not jump by 2 pi.
+pub fn numerical_b_matrix(mol: &[Ion], coords: &[InternalCoordinate], step: f64) -> Result<Vec<Vec<f64>>, GeometryError> {
+    let mut b = vec![vec![0.;3*mol.len()];coords.len()];
+    let mut displaced = mol.to_vec();
+    for atom in 0..mol.len() {
+        for c in 0..3 {
+            let mut values = [Vec::new(), Vec::new()];
+            for (n, sign) in [1., -1.].iter().enumerate() {
+                let ion = &mut displaced[atom];
+                let axis = match c { 0 => &mut ion.x, 1 => &mut ion.y, _ => &mut ion.z };
+                *axis += sign*step;
+                for coord in coords {
+                    values[n].push(coord.value(&displaced)?);
+                }
+                displaced[atom] = mol[atom];
+            }
+            for (m, coord) in coords.iter().enumerate() {
+                let mut diff = values[0][m] - values[1][m];
+                if let InternalCoordinate::Dihedral(..) = coord {
+                    let tau = 2.*std::f64::consts::PI;
+                    diff -= tau*(diff/tau).round();
+                }
+                b[m][3*atom+c] = diff/(2.*step);
+            }
+        }
+    }
+    Ok(b)
+}
@@ -0,0 +1,423 @@
+use std::fs;
+use std::io;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Write;
+use std::str::FromStr;
+
+use crate::elements::{Element, z_for_symbol};
+use crate::error::GeometryError;
+use crate::geometry::{Ion, bonds};
+
+const BOHR_TO_ANGSTROM: f64 = 0.529177210903;
+
+// Coordinates are held internally in angstrom; Units names what an input
+// file uses so the parsers can convert on the way in.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum Units {
+    Angstrom,
+    Bohr,
+}
+
+impl FromStr for Units {
+    type Err = GeometryError;
+
+    fn from_str(s: &str) -> Result<Units, GeometryError> {
+        match s.to_ascii_lowercase().as_str() {
+            "angstrom" => Ok(Units::Angstrom),
+            "bohr" => Ok(Units::Bohr),
+            _ => Err(GeometryError::UnknownUnits(s.to_string())),
+        }
+    }
+}
+
+pub fn to_angstrom(coord: f64, from: Units) -> f64 {
+    match from {
+        Units::Angstrom => coord,
+        Units::Bohr => coord*BOHR_TO_ANGSTROM,
+    }
+}
+
+// "-" names standard input. Gzipped input, recognised by its magic number
+// rather than a .gz name, is decompressed when built with the gzip feature.
+pub fn open_input(filename: &str) -> io::Result<Box<dyn BufRead>> {
+    let mut reader: Box<dyn BufRead> = if filename == "-" {
+        Box::new(BufReader::new(io::stdin()))
+    } else {
+        Box::new(BufReader::new(fs::File::open(filename)?))
+    };
+    if is_gzip(reader.fill_buf()?) {
+        return gunzip(reader);
+    }
+    Ok(reader)
+}
+
+// Data starting with the gzip magic number
+pub(crate) fn is_gzip(data: &[u8]) -> bool {
+    data.starts_with(&[0x1f, 0x8b])
+}
+
+#[cfg(feature = "gzip")]
+fn gunzip(mut reader: Box<dyn BufRead>) -> io::Result<Box<dyn BufRead>> {
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    Ok(Box::new(io::Cursor::new(crate::gzip::decompress(&data)?)))
+}
+
+#[cfg(not(feature = "gzip"))]
+fn gunzip(_reader: Box<dyn BufRead>) -> io::Result<Box<dyn BufRead>> {
+    Err(io::Error::new(io::ErrorKind::InvalidData, "gzip-compressed input needs the gzip feature"))
+}
+
+pub fn file_to_vec<R: BufRead>(reader: R) -> io::Result<Vec<String>> {
+    reader.lines().collect()
+}
+
+// Every frame of a geometry file in any supported format: PDB, legacy
+// `Z x y z` (atom count checked against the records) or XYZ. PDB
+// coordinates are always angstrom, so `units` only applies to the others.
+pub fn parse_frames(lines: &[String], units: Units) -> Result<Vec<Vec<Ion>>, GeometryError> {
+    Ok(parse_frames_with_comments(lines, units)?.into_iter().map(|(_, ions)| ions).collect())
+}
+
+// parse_frames, keeping each frame's comment line for write_xyz to re-emit.
+// Only XYZ has comment lines; PDB and legacy frames get an empty one.
+pub fn parse_frames_with_comments(lines: &[String], units: Units) -> Result<Vec<(String, Vec<Ion>)>, GeometryError> {
+    let uncommented = |frames: Vec<Vec<Ion>>| frames.into_iter().map(|ions| (String::new(), ions)).collect();
+    if is_pdb_format(lines) {
+        Ok(uncommented(parse_pdb(lines)?))
+    } else if is_legacy_format(lines) {
+        let natoms = legacy_atom_count(lines)?;
+        Ok(uncommented(vec![parse_legacy(lines, natoms, units)?]))
+    } else {
+        parse_trajectory_with_comments(lines, units)
+    }
+}
+
+// A single molecule: the only frame of a geometry file, or the first frame
+// of a trajectory
+pub fn parse_molecule(lines: &[String], units: Units) -> Result<Vec<Ion>, GeometryError> {
+    let mut frames = parse_frames(lines, units)?;
+    if frames.is_empty() {
+        return Err(GeometryError::TooFewAtoms { needed: 1, found: 0 });
+    }
+    Ok(frames.swap_remove(0))
+}
+
+pub fn read_molecule_file(path: &str, units: Units) -> io::Result<Vec<Ion>> {
+    let lines = file_to_vec(open_input(path)?)?;
+    Ok(parse_molecule(&lines, units)?)
+}
+
+// Legacy files have no comment line and give atoms as `Z x y z`, so the
+// record after the count is already a fully numeric atom record. A blank
+// line straight after the count is an empty XYZ comment line instead.
+pub fn is_legacy_format(lines: &[String]) -> bool {
+    let records = legacy_records(lines);
+    let count_line = match records.first() {
+        None => return true,
+        Some(&(line_no, _)) => line_no,
+    };
+    if lines.get(count_line).is_some_and(|l| l.trim().is_empty()) {
+        return false;
+    }
+    match records.get(1) {
+        None => true,
+        Some((_, line)) => {
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            tokens.len() == 4
+                && tokens[0].parse::<i32>().is_ok()
+                && tokens[1..].iter().all(|t| t.parse::<f64>().is_ok())
+        },
+    }
+}
+
+// Lines of a legacy file that carry data, with their 1-based line numbers.
+// Blank lines and `#` comments may appear anywhere.
+fn legacy_records(lines: &[String]) -> Vec<(usize, &str)> {
+    lines.iter().enumerate()
+        .filter(|(_, l)| {
+            let t = l.trim();
+            !t.is_empty() && !t.starts_with('#')
+        })
+        .map(|(i, l)| (i+1, l.as_str()))
+        .collect()
+}
+
+// The first record of a legacy file is its atom count. A negative count, or
+// one too large for usize, is a parse error.
+pub fn legacy_atom_count(lines: &[String]) -> Result<usize, GeometryError> {
+    match legacy_records(lines).first() {
+        Some(&(line_no, line)) => parse_field(Some(line.trim()), line_no, "atom count"),
+        None => Err(GeometryError::MissingField { line: 1, field: "atom count" }),
+    }
+}
+
+pub fn parse_legacy(lines: &[String], natoms: usize, units: Units) -> Result<Vec<Ion>, GeometryError> {
+    let records = legacy_records(lines);
+    let atoms = records.get(1..).unwrap_or(&[]);
+    if atoms.len() != natoms {
+        return Err(GeometryError::AtomCountMismatch { declared: natoms, found: atoms.len() });
+    }
+
+    let mut ions = Vec::with_capacity(atoms.len());
+    for &(line_no, line) in atoms {
+        let mut ion_data = line.split_whitespace();
+
+        ions.push(Ion {
+            z_val: parse_field(ion_data.next(), line_no, "atomic number")?,
+            x: to_angstrom(parse_field(ion_data.next(), line_no, "x coordinate")?, units),
+            y: to_angstrom(parse_field(ion_data.next(), line_no, "y coordinate")?, units),
+            z: to_angstrom(parse_field(ion_data.next(), line_no, "z coordinate")?, units),
+        });
+    }
+
+    Ok(ions)
+}
+
+// Standard XYZ: count line, free-text comment line, then `El x y z` rows
+// where El is an element symbol or atomic number. A file may hold several
+// such frames back to back (a trajectory), each with its own atom count.
+// Trailing blank lines are ignored.
+pub fn parse_trajectory(lines: &[String], units: Units) -> Result<Vec<Vec<Ion>>, GeometryError> {
+    Ok(parse_trajectory_with_comments(lines, units)?.into_iter().map(|(_, ions)| ions).collect())
+}
+
+// parse_trajectory with each frame's comment line, verbatim apart from a
+// trailing carriage return
+pub fn parse_trajectory_with_comments(lines: &[String], units: Units) -> Result<Vec<(String, Vec<Ion>)>, GeometryError> {
+    let mut end = lines.len();
+    while end > 0 && lines[end-1].trim().is_empty() {
+        end -= 1;
+    }
+
+    let mut frames = Vec::new();
+    let mut start = 0;
+    while start < end {
+        let (ions, next) = parse_xyz_frame(&lines[..end], start, units)?;
+        // a parsed frame always has its comment line, even with no atoms
+        frames.push((lines[start+1].trim_end_matches('\r').to_string(), ions));
+        start = next;
+    }
+
+    Ok(frames)
+}
+
+// Parse the frame whose count line is lines[start], returning its ions and
+// the index of the line after it
+pub fn parse_xyz_frame(lines: &[String], start: usize, units: Units) -> Result<(Vec<Ion>, usize), GeometryError> {
+    let natoms: usize = parse_field(Some(lines[start].trim()), start+1, "atom count")?;
+    let first_atom = start+2;
+    let end = first_atom+natoms;
+
+    if end > lines.len() {
+        return Err(GeometryError::TruncatedFrame {
+            line: start+1,
+            declared: natoms,
+            found: lines.len().saturating_sub(first_atom),
+        });
+    }
+
+    Ok((parse_xyz_atoms(lines, first_atom, end, units)?, end))
+}
+
+// `El x y z` records lines[first..end]
+fn parse_xyz_atoms(lines: &[String], first: usize, end: usize, units: Units) -> Result<Vec<Ion>, GeometryError> {
+    let mut ions = Vec::with_capacity(end-first);
+    for (i, line) in lines[first..end].iter().enumerate() {
+        let line_no = first+i+1;
+        let mut ion_data = line.split_whitespace();
+
+        ions.push(Ion {
+            z_val: parse_element(ion_data.next(), line_no)?,
+            x: to_angstrom(parse_field(ion_data.next(), line_no, "x coordinate")?, units),
+            y: to_angstrom(parse_field(ion_data.next(), line_no, "y coordinate")?, units),
+            z: to_angstrom(parse_field(ion_data.next(), line_no, "z coordinate")?, units),
+        });
+    }
+    Ok(ions)
+}
+
+// Whether a line reads as an XYZ atom record: an element and three numbers
+fn is_xyz_atom_record(line: &str) -> bool {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    tokens.len() >= 4
+        && parse_element(Some(tokens[0]), 0).is_ok()
+        && tokens[1..4].iter().all(|t| t.parse::<f64>().is_ok())
+}
+
+// parse_frames_with_comments for files whose atom counts may be wrong,
+// e.g. after hand edits. Where a count disagrees with the atom records
+// actually present, the records win: a legacy file uses all of them, and an
+// XYZ frame runs over the unbroken run of atom records after its comment
+// line. Each such frame is listed, by index, with the AtomCountMismatch a
+// strict parse would have failed on so the caller can warn about it.
+pub fn parse_frames_lenient(lines: &[String], units: Units) -> Result<(Vec<(String, Vec<Ion>)>, Vec<(usize, GeometryError)>), GeometryError> {
+    if is_pdb_format(lines) {
+        return Ok((parse_frames_with_comments(lines, units)?, Vec::new()));
+    }
+    if is_legacy_format(lines) {
+        let declared = legacy_atom_count(lines)?;
+        let found = legacy_records(lines).len().saturating_sub(1);
+        let mismatches = if declared == found {
+            Vec::new()
+        } else {
+            vec![(0, GeometryError::AtomCountMismatch { declared, found })]
+        };
+        return Ok((vec![(String::new(), parse_legacy(lines, found, units)?)], mismatches));
+    }
+
+    let mut end = lines.len();
+    while end > 0 && lines[end-1].trim().is_empty() {
+        end -= 1;
+    }
+    let lines = &lines[..end];
+
+    let mut frames = Vec::new();
+    let mut mismatches = Vec::new();
+    let mut start = 0;
+    while start < end {
+        let declared: usize = parse_field(Some(lines[start].trim()), start+1, "atom count")?;
+        let first_atom = start+2;
+        let found = lines.get(first_atom..).unwrap_or(&[]).iter()
+            .take_while(|l| is_xyz_atom_record(l))
+            .count();
+        let (ions, next) = if found == declared || first_atom > end {
+            // counts agree, or there is not even a comment line, which
+            // parse_xyz_frame reports as usual
+            parse_xyz_frame(lines, start, units)?
+        } else {
+            mismatches.push((frames.len(), GeometryError::AtomCountMismatch { declared, found }));
+            (parse_xyz_atoms(lines, first_atom, first_atom+found, units)?, first_atom+found)
+        };
+        frames.push((lines[start+1].trim_end_matches('\r').to_string(), ions));
+        start = next;
+    }
+
+    Ok((frames, mismatches))
+}
+
+// Write `mol` as a standard XYZ file in angstrom. Coordinates use Rust's
+// shortest round-trip formatting, right-aligned, so parsing the output back
+// reproduces them exactly. Newlines in `comment` are replaced by spaces to
+// keep it on the comment line.
+pub fn write_xyz<W: Write>(mol: &[Ion], comment: &str, w: &mut W) -> io::Result<()> {
+    writeln!(w, "{}", mol.len())?;
+    writeln!(w, "{}", comment.replace(['\r', '\n'], " "))?;
+    for ion in mol {
+        writeln!(w, "{:<2} {:>22} {:>22} {:>22}", Element(ion.z_val), ion.x, ion.y, ion.z)?;
+    }
+    Ok(())
+}
+
+// Covalent-bond graph (as found by bonds) in Graphviz DOT: one node per
+// atom labelled with element and index, one edge per bond. Render with
+// e.g. `dot -Tpng`.
+pub fn write_bond_graph<W: Write>(mol: &[Ion], w: &mut W) -> io::Result<()> {
+    let bonds = bonds(mol)?;
+    writeln!(w, "graph molecule {{")?;
+    for (i, ion) in mol.iter().enumerate() {
+        writeln!(w, "    a{} [label=\"{} {}\"];", i, Element(ion.z_val), i)?;
+    }
+    for (j, i, _) in bonds {
+        writeln!(w, "    a{} -- a{};", j, i)?;
+    }
+    writeln!(w, "}}")
+}
+
+// Element given either as a symbol (any case) or as an atomic number
+pub fn parse_element(token: Option<&str>, line: usize) -> Result<i32, GeometryError> {
+    match token.and_then(z_for_symbol) {
+        Some(z) => Ok(z),
+        None => parse_field(token, line, "element"),
+    }
+}
+
+// Parse one whitespace-separated token, reporting the 1-based line number
+// and offending token on failure.
+pub fn parse_field<T: FromStr>(token: Option<&str>, line: usize, field: &'static str) -> Result<T, GeometryError> {
+    match token {
+        None => Err(GeometryError::MissingField { line, field }),
+        Some(tok) => tok.parse::<T>().map_err(|_| GeometryError::ParseField {
+            line,
+            field,
+            token: tok.to_string(),
+        }),
+    }
+}
+
+// PDB files are recognised by their ATOM/HETATM records, which no XYZ
+// count line can start with
+pub fn is_pdb_format(lines: &[String]) -> bool {
+    lines.iter().any(|l| l.starts_with("ATOM  ") || l.starts_with("HETATM"))
+}
+
+// ATOM and HETATM records of a PDB file, one frame per MODEL (or a single
+// frame when there are no MODEL records). Other records are ignored.
+// Coordinates in PDB files are always in angstrom.
+pub fn parse_pdb(lines: &[String]) -> Result<Vec<Vec<Ion>>, GeometryError> {
+    let mut frames = Vec::new();
+    let mut current = Vec::new();
+
+    for (i, line) in lines.iter().enumerate() {
+        let line_no = i+1;
+        if line.starts_with("ATOM  ") || line.starts_with("HETATM") {
+            current.push(Ion {
+                z_val: pdb_element(line, line_no)?,
+                x: parse_field(Some(pdb_column(line, 31, 38)), line_no, "x coordinate")?,
+                y: parse_field(Some(pdb_column(line, 39, 46)), line_no, "y coordinate")?,
+                z: parse_field(Some(pdb_column(line, 47, 54)), line_no, "z coordinate")?,
+            });
+        } else if line.starts_with("ENDMDL") && !current.is_empty() {
+            frames.push(std::mem::take(&mut current));
+        }
+    }
+    if !current.is_empty() {
+        frames.push(current);
+    }
+
+    if frames.is_empty() {
+        return Err(GeometryError::TooFewAtoms { needed: 1, found: 0 });
+    }
+    Ok(frames)
+}
+
+// Trimmed text of the 1-based, inclusive column range, empty when the line
+// is too short
+fn pdb_column(line: &str, first: usize, last: usize) -> &str {
+    let end = last.min(line.len());
+    line.get(first-1..end).unwrap_or("").trim()
+}
+
+// Element from columns 77-78, or failing that from the atom name (columns
+// 13-16), where a one-letter element is right-justified into column 14.
+// Only hydrogens fill all four columns (HE21, HD11, or 1HD1 in older
+// files), so those are hydrogen rather than a two-letter element.
+fn pdb_element(line: &str, line_no: usize) -> Result<i32, GeometryError> {
+    let element = pdb_column(line, 77, 78);
+    if !element.is_empty() {
+        return parse_element(Some(element), line_no);
+    }
+
+    let name = line.get(12..16).unwrap_or("");
+    if name.len() == 4 && !name.contains(' ') {
+        let mut chars = name.chars();
+        let hydrogen = match (chars.next(), chars.next()) {
+            (Some('H'), _) => true,
+            (Some(digit), Some('H')) => digit.is_ascii_digit(),
+            _ => false,
+        };
+        if hydrogen {
+            return Ok(1);
+        }
+    }
+    let letters: String = name.chars().take_while(|c| c.is_ascii_alphabetic() || *c == ' ')
+        .filter(|c| c.is_ascii_alphabetic())
+        .collect();
+    let two_letter = !name.starts_with(' ') && letters.len() >= 2;
+    let symbol = if two_letter { &letters[..2] } else { letters.get(..1).unwrap_or("") };
+    match z_for_symbol(symbol) {
+        Some(z) => Ok(z),
+        None => parse_element(Some(name.trim()), line_no),
+    }
+}
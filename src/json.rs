@@ -0,0 +1,242 @@
+use std::fmt;
+
+use crawford_group_projects::Ion;
+use crawford_group_projects::elements::symbol_for_z;
+use crawford_group_projects::geometry::*;
+use crawford_group_projects::inertia::*;
+use crawford_group_projects::composition::{element_counts, molecular_formula, molecular_mass};
+
+use crate::{Deltas, in_degrees};
+use crate::cli::Options;
+
+// Minimal JSON value, enough for writing reports without pulling in serde
+#[derive(Debug,Clone,PartialEq)]
+pub enum Json {
+    Null,
+    Num(f64),
+    Int(i64),
+    Str(String),
+    Arr(Vec<Json>),
+    Obj(Vec<(&'static str, Json)>),
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            // JSON has no NaN or infinity
+            Json::Num(x) if !x.is_finite() => write!(f, "null"),
+            Json::Num(x) => write!(f, "{:?}", x),
+            Json::Int(n) => write!(f, "{}", n),
+            Json::Str(s) => write_json_str(f, s),
+            Json::Arr(items) => {
+                write!(f, "[")?;
+                for (n, item) in items.iter().enumerate() {
+                    if n > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            },
+            Json::Obj(fields) => {
+                write!(f, "{{")?;
+                for (n, (key, value)) in fields.iter().enumerate() {
+                    if n > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_json_str(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            },
+        }
+    }
+}
+
+fn write_json_str(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+fn idx(i: usize) -> Json {
+    Json::Int(i as i64)
+}
+
+fn xyz(v: (f64,f64,f64)) -> Json {
+    Json::Arr(vec![Json::Num(v.0), Json::Num(v.1), Json::Num(v.2)])
+}
+
+fn pair(i: usize, j: usize, value: f64) -> Json {
+    Json::Obj(vec![("i", idx(i)), ("j", idx(j)), ("value", Json::Num(value))])
+}
+
+fn pairs(pairs: &[(usize,usize,f64)]) -> Json {
+    Json::Arr(pairs.iter().map(|&(i,j,value)| pair(i, j, value)).collect())
+}
+
+// (vertex, a, b, angle) tuples as from bond_angles, those within the
+// --angle-min/--angle-max range
+fn angle_triples(triples: &[(usize,usize,usize,f64)], opts: &Options) -> Json {
+    Json::Arr(triples.iter().filter(|a| opts.angle_in_range(a.3)).map(|&(vertex,a,b,value)| Json::Obj(vec![
+        ("vertex", idx(vertex)), ("a", idx(a)), ("b", idx(b)), ("value", Json::Num(opts.angle(value))),
+    ])).collect())
+}
+
+// One object per frame of each input file, with the frame's XYZ comment
+// line ("" for other formats). Sections follow the analysis flags; a section
+// that cannot be computed for this molecule is null. Lengths are in
+// angstrom; angles are in radians unless --degrees was given, as recorded
+// in "angle_units"; "linear_angles" are the bonded angles within
+// LINEAR_ANGLE_TOL of 180 degrees and "dihedral_angles" the torsions along
+// bonded chains. With --atoms the length, angle and dihedral sections
+// cover only "selected_atoms" (coordination numbers in that order), still
+// indexed as in the whole molecule. With --contacts, "contacts" lists the
+// pairs closer than that many angstrom. With --strain, "strained_angles" lists
+// the bonded angles past that many degrees from their VSEPR ideal; with
+// --histogram, "angle_histogram" bins bond_angles by bin start, always in
+// degrees like the bin width. With --compare, "bond_length_deltas" and
+// "bond_angle_deltas" give each reference bond and bonded angle, its value
+// there and the change to this geometry. With --reference,
+// "rmsd_to_reference" is the RMSD after Kabsch alignment onto it.
+pub fn report(filename: &str, (frame, comment): (usize, &str), mol: &[Ion], (sel, index): &(Vec<Ion>, Vec<usize>), deltas: Option<&Deltas>, rmsd: Option<f64>, opts: &Options) -> Json {
+    let mut fields = vec![
+        ("file", Json::Str(filename.to_string())),
+        ("frame", idx(frame)),
+        ("comment", Json::Str(comment.to_string())),
+        ("natoms", idx(mol.len())),
+        ("formula", Json::Str(molecular_formula(mol))),
+        ("element_counts", Json::Arr(element_counts(mol).iter().map(|&(z, n)| Json::Obj(vec![
+            ("z_val", Json::Int(z as i64)),
+            ("count", idx(n)),
+        ])).collect())),
+        ("molecular_mass", molecular_mass(mol).map_or(Json::Null, Json::Num)),
+        ("angle_units", Json::Str(opts.angle_units().to_string())),
+        ("atoms", Json::Arr(mol.iter().enumerate().map(|(n, ion)| Json::Obj(vec![
+            ("index", idx(n)),
+            ("z_val", Json::Int(ion.z_val as i64)),
+            ("symbol", symbol_for_z(ion.z_val).map_or(Json::Null, |s| Json::Str(s.to_string()))),
+            ("x", Json::Num(ion.x)),
+            ("y", Json::Num(ion.y)),
+            ("z", Json::Num(ion.z)),
+        ])).collect())),
+    ];
+
+    if opts.atoms.is_some() {
+        fields.push(("selected_atoms", Json::Arr(index.iter().map(|&i| idx(i)).collect())));
+    }
+    let remap = |p: &[(usize,usize,f64)]| -> Vec<(usize,usize,f64)> {
+        p.iter().map(|&(i,j,v)| (index[i],index[j],v)).collect()
+    };
+    let remap3 = |t: &[(usize,usize,usize,f64)]| -> Vec<(usize,usize,usize,f64)> {
+        t.iter().map(|&(i,j,k,v)| (index[i],index[j],index[k],v)).collect()
+    };
+    if opts.lengths {
+        fields.push(("bond_lengths", all_bond_lengths(sel).map_or(Json::Null, |lengths| {
+            let mut upper = Vec::new();
+            for (i, row) in lengths.iter().enumerate() {
+                for (j, &l) in row.iter().enumerate().skip(i+1) {
+                    upper.push((index[i],index[j],l));
+                }
+            }
+            pairs(&upper)
+        })));
+        fields.push(("distance_extrema", distance_extrema(sel).map_or(Json::Null, |(min, max)| {
+            Json::Obj(vec![
+                ("shortest", pair(index[min.0], index[min.1], min.2)),
+                ("longest", pair(index[max.0], index[max.1], max.2)),
+            ])
+        })));
+        fields.push(("bonds", bonds(sel).map_or(Json::Null, |b| pairs(&remap(&b)))));
+        if let Some(cutoff) = opts.contacts {
+            fields.push(("contacts", contacts_within(sel, cutoff).map_or(Json::Null, |c| pairs(&remap(&c)))));
+        }
+        fields.push(("coordination_numbers", coordination_numbers(sel).map_or(Json::Null, |c| {
+            Json::Arr(c.iter().map(|&n| idx(n)).collect())
+        })));
+        fields.push(("fragments", molecular_fragments(sel).map_or(Json::Null, |f| {
+            Json::Arr(f.iter().map(|atoms| Json::Arr(atoms.iter().map(|&i| idx(index[i])).collect())).collect())
+        })));
+        fields.push(("intermolecular_distances", intermolecular_distances(sel).map_or(Json::Null, |d| pairs(&remap(&d)))));
+    }
+    if opts.angles {
+        fields.push(("bond_angles", bond_angles(sel).map_or(Json::Null, |a| angle_triples(&remap3(&a), opts))));
+        if let Some(width) = opts.histogram {
+            fields.push(("angle_histogram", bond_angles(sel).map_or(Json::Null, |a| {
+                Json::Arr(angle_histogram(&in_degrees(&a), width, 180.).iter().map(|&(start, count)| Json::Obj(vec![
+                    ("start_degrees", Json::Num(start)), ("count", idx(count)),
+                ])).collect())
+            })));
+        }
+        fields.push(("bonded_angles", bonded_angles(sel).map_or(Json::Null, |a| angle_triples(&remap3(&a), opts))));
+        fields.push(("linear_angles", linear_angles(sel, LINEAR_ANGLE_TOL).map_or(Json::Null, |a| angle_triples(&remap3(&a), opts))));
+        fields.push(("out_of_plane_angles", out_of_plane_angles(sel).map_or(Json::Null, |oop| {
+            Json::Arr(oop.iter().map(|&(i,j,k,l,value)| Json::Obj(vec![
+                ("i", idx(index[i])), ("j", idx(index[j])), ("k", idx(index[k])), ("l", idx(index[l])),
+                ("value", Json::Num(opts.angle(value))),
+            ])).collect())
+        })));
+        fields.push(("vertex_angle_ranges", vertex_angle_ranges(sel).map_or(Json::Null, |r| {
+            Json::Arr(r.iter().map(|&(j,min,max)| Json::Obj(vec![
+                ("vertex", idx(index[j])), ("min", Json::Num(opts.angle(min))), ("max", Json::Num(opts.angle(max))),
+            ])).collect())
+        })));
+        if let Some(tol) = opts.strain {
+            fields.push(("strained_angles", strained_angles(sel, tol.to_radians()).map_or(Json::Null, |strained| {
+                Json::Arr(strained.iter().filter(|a| opts.angle_in_range(a.3)).map(|&(j,a,b,theta,ideal)| Json::Obj(vec![
+                    ("vertex", idx(index[j])), ("a", idx(index[a])), ("b", idx(index[b])),
+                    ("value", Json::Num(opts.angle(theta))), ("ideal", Json::Num(opts.angle(ideal))),
+                ])).collect())
+            })));
+        }
+    }
+    if opts.dihedrals {
+        fields.push(("dihedral_angles", dihedral_angles(sel).map_or(Json::Null, |d| {
+            Json::Arr(d.iter().map(|&(i,j,k,l,value)| Json::Obj(vec![
+                ("i", idx(index[i])), ("j", idx(index[j])), ("k", idx(index[k])), ("l", idx(index[l])),
+                ("value", Json::Num(opts.angle(value))),
+            ])).collect())
+        })));
+    }
+    if opts.com {
+        fields.push(("center_of_mass", center_of_mass(mol).map_or(Json::Null, xyz)));
+        fields.push(("centroid", centroid(mol).map_or(Json::Null, xyz)));
+        fields.push(("radius_of_gyration", radius_of_gyration(mol).map_or(Json::Null, Json::Num)));
+    }
+    if opts.inertia {
+        fields.push(("principal_moments", principal_moments(mol).map_or(Json::Null, |m| {
+            Json::Arr(m.iter().map(|&x| Json::Num(x)).collect())
+        })));
+        fields.push(("rotational_constants", rotational_constants(mol).map_or(Json::Null, |c| {
+            Json::Arr(c.iter().map(|b| b.map_or(Json::Null, Json::Num)).collect())
+        })));
+        fields.push(("rotor_type", rotor_type(mol).map_or(Json::Null, |r| {
+            Json::Str(format!("{:?}", r))
+        })));
+    }
+    if let Some((lengths, angles)) = deltas {
+        fields.push(("bond_length_deltas", Json::Arr(lengths.iter().map(|&(i,j,r,dr)| Json::Obj(vec![
+            ("i", idx(i)), ("j", idx(j)), ("reference", Json::Num(r)), ("delta", Json::Num(dr)),
+        ])).collect())));
+        fields.push(("bond_angle_deltas", Json::Arr(angles.iter().map(|&(vertex,a,b,theta,dtheta)| Json::Obj(vec![
+            ("vertex", idx(vertex)), ("a", idx(a)), ("b", idx(b)),
+            ("reference", Json::Num(opts.angle(theta))), ("delta", Json::Num(opts.angle(dtheta))),
+        ])).collect())));
+    }
+    if let Some(rmsd) = rmsd {
+        fields.push(("rmsd_to_reference", Json::Num(rmsd)));
+    }
+
+    Json::Obj(fields)
+}
@@ -0,0 +1,25 @@
+#![allow(clippy::type_complexity)]
+
+pub mod elements;
+pub mod error;
+pub mod float;
+pub mod geometry;
+pub mod inertia;
+pub mod internal;
+pub mod align;
+pub mod charges;
+pub mod composition;
+pub mod io;
+pub mod linalg;
+pub mod molecule;
+pub mod periodic;
+pub mod shape;
+pub mod symmetry;
+#[cfg(feature = "gzip")]
+mod gzip;
+mod grid;
+
+pub use error::GeometryError;
+pub use geometry::Ion;
+pub use io::Units;
+pub use molecule::Molecule;
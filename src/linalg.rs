@@ -0,0 +1,81 @@
+use crate::float::Float;
+
+pub(crate) fn dot<T: Float>(a: [T;3], b: [T;3]) -> T {
+    a[0]*b[0] + a[1]*b[1] + a[2]*b[2]
+}
+
+pub(crate) fn cross<T: Float>(a: [T;3], b: [T;3]) -> [T;3] {
+    [a[1]*b[2]-a[2]*b[1], a[2]*b[0]-a[0]*b[2], a[0]*b[1]-a[1]*b[0]]
+}
+
+// Eigendecomposition of a symmetric 3x3 matrix such as an inertia tensor or
+// a scatter matrix: eigenvalues ascending, orthonormal eigenvectors as the
+// matching columns. The input is assumed symmetric and not checked.
+pub fn eig_sym3(m: [[f64;3];3]) -> ([f64;3],[[f64;3];3]) {
+    jacobi_eigen(m)
+}
+
+// Cyclic Jacobi eigensolver for a small symmetric matrix. Returns the
+// eigenvalues in ascending order and the matching eigenvectors as columns.
+pub(crate) fn jacobi_eigen<const N: usize>(m: [[f64;N];N]) -> ([f64;N],[[f64;N];N]) {
+    let mut a = m;
+    let mut v = [[0.;N];N];
+    for (i, row) in v.iter_mut().enumerate() {
+        row[i] = 1.;
+    }
+    let norm_sq: f64 = a.iter().flatten().map(|x| x*x).sum();
+
+    for _ in 0..50 {
+        let off_sq: f64 = a.iter().enumerate()
+            .flat_map(|(p, row)| row[p+1..].iter())
+            .map(|x| x*x)
+            .sum();
+        if off_sq <= f64::EPSILON*f64::EPSILON*norm_sq {
+            break;
+        }
+        for p in 0..N {
+            for q in p+1..N {
+                if a[p][q] == 0. {
+                    continue;
+                }
+                let theta = (a[q][q]-a[p][p])/(2.*a[p][q]);
+                let t = theta.signum()/(theta.abs() + (theta*theta + 1.).sqrt());
+                let c = 1./(t*t + 1.).sqrt();
+                let s = t*c;
+                for row in a.iter_mut() {
+                    let (akp, akq) = (row[p], row[q]);
+                    row[p] = c*akp - s*akq;
+                    row[q] = s*akp + c*akq;
+                }
+                let (row_p, row_q) = (a[p], a[q]);
+                for (k, (apk, aqk)) in row_p.iter().zip(row_q.iter()).enumerate() {
+                    a[p][k] = c*apk - s*aqk;
+                    a[q][k] = s*apk + c*aqk;
+                }
+                for row in v.iter_mut() {
+                    let (vkp, vkq) = (row[p], row[q]);
+                    row[p] = c*vkp - s*vkq;
+                    row[q] = s*vkp + c*vkq;
+                }
+            }
+        }
+    }
+
+    let mut order = [0;N];
+    for (i, o) in order.iter_mut().enumerate() {
+        *o = i;
+    }
+    // total_cmp, so a matrix with NaN entries gives NaN eigenvalues rather
+    // than a panic
+    order.sort_by(|&i, &j| a[i][i].total_cmp(&a[j][j]));
+    let mut values = [0.;N];
+    let mut vectors = [[0.;N];N];
+    for (col, &i) in order.iter().enumerate() {
+        values[col] = a[i][i];
+        for row in 0..N {
+            vectors[row][col] = v[row][i];
+        }
+    }
+
+    (values, vectors)
+}
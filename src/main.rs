@@ -1,157 +1,502 @@
+#![allow(clippy::type_complexity)]
+
+mod cli;
+mod csv;
+mod json;
+mod table;
+
 use std::io;
-use std::io::BufReader;
-use std::io::BufRead;
 use std::io::Error;
 use std::io::ErrorKind;
 use std::env;
-use std::fs;
+use std::process;
+use std::time::Instant;
+
+use crawford_group_projects::{GeometryError, Ion, Molecule, Units};
+use crawford_group_projects::geometry::*;
+use crawford_group_projects::inertia::*;
+use crawford_group_projects::align::{bond_angle_deltas, bond_length_deltas, kabsch_align, rmsd};
+use crawford_group_projects::composition::{element_counts, molecular_formula, molecular_mass};
+use crawford_group_projects::io::{to_angstrom, open_input, file_to_vec, parse_frames_lenient, parse_frames_with_comments, write_bond_graph};
+use cli::{Format, Options, USAGE};
 
-fn main() -> io::Result<()> {
+// Bond length and bonded angle changes from the --compare reference, as
+// from bond_length_deltas and bond_angle_deltas
+pub type Deltas = (Vec<(usize,usize,f64,f64)>, Vec<(usize,usize,usize,f64,f64)>);
 
-    let mut args = env::args();
-    args.next();
+// A frame's comment line (empty outside XYZ input) and its geometry
+type Frame = (String, Vec<Ion>);
 
-    for arg in args {
-        let lines = file_to_vec(arg)?;
+// Errors are reported as "error: <message>" with exit status 1, rather
+// than in the Debug form main's own Result would print
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
 
-        // Read first line (No. atoms in system)
-        let natoms: i32 = (&lines[0]).parse().unwrap();
+fn run() -> io::Result<()> {
 
-        if (lines.len()-1) as i32 != natoms {
-            return Err(
-                Error::new(
-                    ErrorKind::InvalidData,
-                    "Number of atoms != number of data points"
-                )
-            );
+    let opts = Options::parse(env::args().skip(1))?;
+    if opts.help {
+        print!("{}", USAGE);
+        return Ok(());
+    }
+
+    let compare = opts.compare.as_deref().map(|file| read_reference(file, &opts)).transpose()?;
+    let reference = opts.reference.as_deref().map(|file| read_reference(file, &opts)).transpose()?;
+
+    if opts.merge {
+        let mut merged = Vec::new();
+        for arg in &opts.files {
+            let frames = read_frames(arg, &opts)?;
+            let (_, (_, ions)) = select_frames(arg, frames, &opts)?.swap_remove(0);
+            merged = merge(&merged, &ions);
         }
+        analyse(&opts.files.join("+"), 1, vec![(0, (String::new(), merged))], &opts, compare.as_ref(), reference.as_ref())?;
+    } else {
+        for arg in &opts.files {
+            let frames = read_frames(arg, &opts)?;
+            let nframes = frames.len();
+            analyse(arg, nframes, select_frames(arg, frames, &opts)?, &opts, compare.as_ref(), reference.as_ref())?;
+        }
+    }
 
-        // Read all other lines to array of atom data
-        // negative Z values are used as errors
-        let mut ions: Vec<Ion> = vec![
-            Ion {z_val:-1_i32,x:0.,y:0.,z:0.};
-            lines.len()-1
-        ];
+    Ok(())
+}
+
+// Report on each (index, geometry) frame of the system `arg`, which has
+// `nframes` frames in all, in the chosen format
+fn analyse(arg: &str, nframes: usize, frames: Vec<(usize, Frame)>, opts: &Options, compare: Option<&Molecule>, reference: Option<&Molecule>) -> io::Result<()> {
+    for (n, (frame, (comment, ions))) in frames.into_iter().enumerate() {
+        let ions = &Molecule::new(ions).map_err(|e| in_file(arg, e))?;
+        for (j, i, l) in find_overlaps(ions, OVERLAP_TOL) {
+            eprintln!("warning: {}: ions {} and {} overlap ({} apart)", arg, j, i, opts.number(l));
+        }
 
-        for i in 1..lines.len() {
-            let mut ion_data = (&lines[i]).split_whitespace();
+        if opts.units == Units::Angstrom && looks_like_bohr(ions) {
+            eprintln!("warning: {}: no bonds in angstrom but some in bohr; the coordinates may need --units bohr", arg);
+        }
 
-            ions[i-1] = Ion {
-                z_val: ion_data.next().unwrap().parse::<i32>().unwrap(),
-                x: ion_data.next().unwrap().parse::<f64>().unwrap(),
-                y: ion_data.next().unwrap().parse::<f64>().unwrap(),
-                z: ion_data.next().unwrap().parse::<f64>().unwrap(),
-            }
+        let selected = opts.selected(ions).map_err(|e| in_file(arg, e))?;
+        let deltas: Option<Deltas> = match (compare, &opts.compare) {
+            (Some(r), Some(file)) => Some((
+                bond_length_deltas(r, ions).map_err(|e| against(arg, file, e))?,
+                bond_angle_deltas(r, ions).map_err(|e| against(arg, file, e))?,
+            )),
+            _ => None,
+        };
+        let rmsd = match (reference, &opts.reference) {
+            (Some(r), Some(file)) => Some(
+                kabsch_align(ions, r).and_then(|aligned| rmsd(&aligned, r)).map_err(|e| against(arg, file, e))?
+            ),
+            _ => None,
+        };
 
+        match opts.format {
+            Format::Human if opts.batch() => print_summary(arg, frame, nframes, ions, rmsd, opts),
+            Format::Human => {
+                // on the first frame analysed, which --frame may make a later one
+                if opts.files.len() > 1 && !opts.merge && n == 0 && !opts.no_header {
+                    println!("==> {} <==", arg);
+                }
+                if nframes > 1 && !opts.no_header {
+                    println!("frame {}:", frame);
+                }
+                print_report(ions, &comment, &selected, opts)?;
+                if let Some(deltas) = &deltas {
+                    print_deltas(ions, deltas, opts);
+                }
+                if let Some(rmsd) = rmsd {
+                    record(opts, "rmsd to reference after alignment (angstrom)", &opts.number(rmsd));
+                }
+            },
+            Format::Json => {
+                let start = Instant::now();
+                let report = json::report(arg, (frame, &comment), ions, &selected, deltas.as_ref(), rmsd, opts);
+                elapsed(opts, "report", start);
+                println!("{}", report)
+            },
+            Format::Csv => print!("{}", csv::report(ions, &selected, opts)),
+            Format::Dot => write_bond_graph(ions, &mut io::stdout().lock()).map_err(|e| in_file(arg, e))?,
         }
+    }
+    Ok(())
+}
 
-        let bond_lengths = all_bond_lengths(&ions).unwrap();
-        let bond_angles = bond_angles(&ions).unwrap();
+// Each section stands alone: one that cannot be computed for this molecule
+// prints a note and the rest of the report carries on. Lengths, angles and
+// dihedrals cover the (sel, index) atoms from Options::selected, labelled
+// with their indices in the whole molecule.
+fn print_report(ions: &[Ion], comment: &str, (sel, index): &(Vec<Ion>, Vec<usize>), opts: &Options) -> io::Result<()> {
+    let num = |x: f64| opts.number(x);
+    let angle = |a: f64| opts.number(opts.angle(a));
+    let label = |i: usize| table::atom_label(i, &ions[i]);
+    let sel_label = |n: usize| label(index[n]);
+    let indent = if opts.no_header { "" } else { "    " };
 
-        //testing output
-        println!("number of atoms:\n    {:?}", natoms);
+    if !opts.quiet {
+        record(opts, "number of atoms", &ions.len().to_string());
+        if !comment.trim().is_empty() || opts.no_header {
+            record(opts, "comment", comment);
+        }
+        record(opts, "formula", &molecular_formula(ions));
+        let counts: Vec<String> = element_counts(ions).iter()
+            .map(|&(z, n)| format!("{} {}", table::element_label(z), n))
+            .collect();
+        record(opts, "elements", &counts.join(", "));
+        match molecular_mass(ions) {
+            Ok(mass) => record(opts, "molecular mass (amu)", &num(mass)),
+            Err(e) => not_available(opts, "molecular mass", &e),
+        }
+        let atoms: Vec<Vec<String>> = ions.iter().enumerate()
+            .map(|(i, ion)| vec![label(i), num(ion.x), num(ion.y), num(ion.z)])
+            .collect();
+        section(opts, "atoms (angstrom)", &table::columns(&atoms, indent));
+    }
+    if opts.debug {
         println!("ion data:\n   {:?}", ions);
-        println!("all bond lengths:\n    {:?}", bond_lengths);
-        println!("all bond angles:\n    {:?}", bond_angles);
     }
-
+    if opts.lengths {
+        let start = Instant::now();
+        let labels: Vec<String> = (0..sel.len()).map(sel_label).collect();
+        // a lone atom has no distances, so no bonds or coordination either
+        match all_bond_lengths(sel) {
+            Ok(bond_lengths) => {
+                let matrix = if opts.no_header {
+                    // the matrix without its row of column labels
+                    let rows: Vec<Vec<String>> = bond_lengths.iter().enumerate()
+                        .map(|(i, row)| std::iter::once(labels[i].clone()).chain(row.iter().map(|&l| num(l))).collect())
+                        .collect();
+                    table::columns(&rows, indent)
+                } else {
+                    table::distance_matrix(&labels, &bond_lengths, opts.precision, indent)
+                };
+                section(opts, "all bond lengths (angstrom)", &matrix);
+                if let Ok((min, max)) = distance_extrema(sel) {
+                    // bare rows are whitespace-separated fields, without the dash
+                    let dash = if opts.no_header { " " } else { " - " };
+                    record(opts, "shortest distance", &format!("{}{}{}  {}", sel_label(min.0), dash, sel_label(min.1), num(min.2)));
+                    record(opts, "longest distance", &format!("{}{}{}  {}", sel_label(max.0), dash, sel_label(max.1), num(max.2)));
+                }
+                match bonds(sel) {
+                    Ok(bonds) => {
+                        let rows: Vec<Vec<String>> = bonds.iter()
+                            .map(|&(j,i,l)| vec![sel_label(j), sel_label(i), num(l)])
+                            .collect();
+                        if rows.is_empty() && !opts.no_header {
+                            println!("bonds: none");
+                        } else {
+                            section(opts, "bonds", &table::columns(&rows, indent));
+                        }
+                    },
+                    Err(e) => not_available(opts, "bonds", &e),
+                }
+                if let Some(cutoff) = opts.contacts {
+                    let rows: Vec<Vec<String>> = contacts_within(sel, cutoff)?.iter()
+                        .map(|&(j,i,l)| vec![sel_label(j), sel_label(i), num(l)])
+                        .collect();
+                    section(opts, &format!("contacts within {} angstrom", cutoff), &table::columns(&rows, indent));
+                }
+                match coordination_numbers(sel) {
+                    Ok(coordination) => {
+                        let rows: Vec<Vec<String>> = coordination.iter().enumerate()
+                            .map(|(i, n)| vec![sel_label(i), n.to_string()])
+                            .collect();
+                        section(opts, "coordination numbers", &table::columns(&rows, indent));
+                    },
+                    Err(e) => not_available(opts, "coordination numbers", &e),
+                }
+                if let Ok(fragments) = molecular_fragments(sel) {
+                    if fragments.len() > 1 || opts.no_header {
+                        let rows: Vec<Vec<String>> = fragments.iter().enumerate()
+                            .map(|(n, atoms)| vec![n.to_string(), atoms.iter().map(|&i| index[i].to_string()).collect::<Vec<_>>().join(",")])
+                            .collect();
+                        section(opts, "fragments", &table::columns(&rows, indent));
+                        if let Ok(distances) = intermolecular_distances(sel) {
+                            let rows: Vec<Vec<String>> = distances.iter()
+                                .map(|&(i,j,l)| vec![sel_label(i), sel_label(j), num(l)])
+                                .collect();
+                            section(opts, "intermolecular distances (angstrom)", &table::columns(&rows, indent));
+                        }
+                    }
+                }
+            },
+            Err(e) => not_available(opts, "lengths", &e),
+        }
+        elapsed(opts, "lengths", start);
+    }
+    if opts.angles {
+        let start = Instant::now();
+        // angle listings keep to the --angle-min/--angle-max range
+        let triples = |angles: &[(usize,usize,usize,f64)]| -> Vec<Vec<String>> {
+            angles.iter()
+                .filter(|a| opts.angle_in_range(a.3))
+                .map(|&(j,a,b,theta)| vec![sel_label(a), sel_label(j), sel_label(b), angle(theta)])
+                .collect()
+        };
+        match bond_angles(sel) {
+            Ok(bond_angles) => {
+                section(opts, &format!("all bond angles ({})", opts.angle_units()), &table::columns(&triples(&bond_angles), indent));
+                if let Some(width) = opts.histogram {
+                    let rows: Vec<Vec<String>> = angle_histogram(&in_degrees(&bond_angles), width, 180.).iter()
+                        .map(|&(start, count)| {
+                            let (from, to) = (format!("{:.1}", start), format!("{:.1}", start+width));
+                            if opts.no_header {
+                                vec![from, to, count.to_string()]
+                            } else {
+                                vec![from, "-".to_string(), to, count.to_string()]
+                            }
+                        })
+                        .collect();
+                    section(opts, "bond angle histogram (degrees)", &table::columns(&rows, indent));
+                }
+                if let Ok(bonded_angles) = bonded_angles(sel) {
+                    section(opts, &format!("bonded angles ({})", opts.angle_units()), &table::columns(&triples(&bonded_angles), indent));
+                }
+                if let Ok(linear) = linear_angles(sel, LINEAR_ANGLE_TOL) {
+                    if !linear.is_empty() || opts.no_header {
+                        section(opts, &format!("linear angles ({})", opts.angle_units()), &table::columns(&triples(&linear), indent));
+                    }
+                }
+                if let Ok(oop) = out_of_plane_angles(sel) {
+                    if !oop.is_empty() || opts.no_header {
+                        let rows: Vec<Vec<String>> = oop.iter()
+                            .map(|&(i,j,k,l,theta)| vec![sel_label(i), sel_label(j), sel_label(k), sel_label(l), angle(theta)])
+                            .collect();
+                        section(opts, &format!("out-of-plane angles ({})", opts.angle_units()), &table::columns(&rows, indent));
+                    }
+                }
+                if let Ok(ranges) = vertex_angle_ranges(sel) {
+                    let rows: Vec<Vec<String>> = ranges.iter()
+                        .map(|&(j,min,max)| vec![sel_label(j), angle(min), angle(max)])
+                        .collect();
+                    section(opts, &format!("smallest and largest angle at each vertex ({})", opts.angle_units()), &table::columns(&rows, indent));
+                }
+                if let Some(tol) = opts.strain {
+                    if let Ok(strained) = strained_angles(sel, tol.to_radians()) {
+                        let rows: Vec<Vec<String>> = strained.iter()
+                            .filter(|a| opts.angle_in_range(a.3))
+                            .map(|&(j,a,b,theta,ideal)| vec![
+                                sel_label(a), sel_label(j), sel_label(b), angle(theta), angle(ideal),
+                            ])
+                            .collect();
+                        if !rows.is_empty() || opts.no_header {
+                            section(opts, &format!("angles more than {} degrees from ideal ({})", tol, opts.angle_units()), &table::columns(&rows, indent));
+                        }
+                    }
+                }
+            },
+            Err(e) => not_available(opts, "angles", &e),
+        }
+        elapsed(opts, "angles", start);
+    }
+    if opts.com {
+        let start = Instant::now();
+        match center_of_mass(ions) {
+            Ok(com) => record(opts, "center of mass (angstrom)", &format!("{} {} {}", num(com.0), num(com.1), num(com.2))),
+            Err(e) => not_available(opts, "center of mass", &e),
+        }
+        if let Ok(c) = centroid(ions) {
+            record(opts, "centroid (angstrom)", &format!("{} {} {}", num(c.0), num(c.1), num(c.2)));
+        }
+        if let Ok(rg) = radius_of_gyration(ions) {
+            record(opts, "radius of gyration (angstrom)", &num(rg));
+        }
+        elapsed(opts, "com", start);
+    }
+    if opts.inertia {
+        let start = Instant::now();
+        match principal_moments(ions) {
+            Ok(moments) => record(opts, "principal moments of inertia (amu angstrom^2)", &format!("{} {} {}", num(moments[0]), num(moments[1]), num(moments[2]))),
+            Err(e) => not_available(opts, "inertia", &e),
+        }
+        if let Ok(constants) = rotational_constants(ions) {
+            let rows: Vec<Vec<String>> = ["A", "B", "C"].iter().zip(&constants)
+                .map(|(name, b)| vec![name.to_string(), b.map_or("-".to_string(), num)])
+                .collect();
+            section(opts, "rotational constants (cm^-1)", &table::columns(&rows, indent));
+        }
+        if let Ok(rotor) = rotor_type(ions) {
+            record(opts, "rotor type", &rotor.to_string());
+        }
+        elapsed(opts, "inertia", start);
+    }
+    if opts.dihedrals {
+        let start = Instant::now();
+        match dihedral_angles(sel) {
+            Ok(dihedral_angles) => {
+                let rows: Vec<Vec<String>> = dihedral_angles.iter()
+                    .map(|&(i,j,k,l,phi)| vec![sel_label(i), sel_label(j), sel_label(k), sel_label(l), angle(phi)])
+                    .collect();
+                section(opts, &format!("dihedral angles ({})", opts.angle_units()), &table::columns(&rows, indent));
+            },
+            Err(e) => not_available(opts, "dihedrals", &e),
+        }
+        elapsed(opts, "dihedrals", start);
+    }
     Ok(())
 }
 
+// Change from the --compare reference in each of its bonds and bonded angles
+fn print_deltas(ions: &[Ion], (lengths, angles): &Deltas, opts: &Options) {
+    let label = |i: usize| table::atom_label(i, &ions[i]);
+    let num = |x: f64| opts.number(x);
+    let angle = |a: f64| opts.number(opts.angle(a));
+    let indent = if opts.no_header { "" } else { "    " };
 
-fn file_to_vec(filename: String) -> io::Result<Vec<String>> {
-    let file_in = fs::File::open(filename)?;
-    let file_reader = BufReader::new(file_in);
-
-    Ok(file_reader.lines().filter_map(io::Result::ok).collect())
+    let rows: Vec<Vec<String>> = lengths.iter()
+        .map(|&(j,i,r,dr)| vec![label(j), label(i), num(r), num(r+dr), num(dr)])
+        .collect();
+    section(opts, "bond length changes (angstrom)", &table::columns(&rows, indent));
+    let rows: Vec<Vec<String>> = angles.iter()
+        .map(|&(j,a,b,theta,dtheta)| vec![
+            label(a), label(j), label(b), angle(theta), angle(theta+dtheta), angle(dtheta),
+        ])
+        .collect();
+    section(opts, &format!("bond angle changes ({})", opts.angle_units()), &table::columns(&rows, indent));
 }
 
-
-#[derive(Debug,Clone,Copy,PartialEq)]
-pub struct Ion {
-    pub z_val: i32,
-    pub x: f64,
-    pub y: f64,
-    pub z: f64,
+// (vertex, a, b, angle) tuples with the angle converted to degrees, for
+// --histogram bin widths
+fn in_degrees(angles: &[(usize,usize,usize,f64)]) -> Vec<(usize,usize,usize,f64)> {
+    angles.iter().map(|&(j,a,b,theta)| (j,a,b,theta.to_degrees())).collect()
 }
 
-impl Ion {
+// A titled block of rows. With --no-header only the rows are printed, each
+// section ending in a blank line so they still split apart, e.g. as the
+// records of awk's paragraph mode (RS="").
+fn section(opts: &Options, title: &str, rows: &str) {
+    if opts.no_header {
+        println!("{}", rows);
+    } else {
+        print!("{}:\n{}", title, rows);
+    }
+}
 
-    pub fn bond_length(&self, other: &Ion) -> f64 {
-        ( (self.x-other.x).powi(2)
-        + (self.y-other.y).powi(2)
-        + (self.z-other.z).powi(2)
-        ).sqrt()
+// A one-line section, "title: value", or just the value with --no-header
+fn record(opts: &Options, title: &str, value: &str) {
+    if opts.no_header {
+        println!("{}\n", value);
+    } else {
+        println!("{}: {}", title, value);
     }
+}
 
-    pub fn bond_vector(&self, other: &Ion) -> (f64,f64,f64) {
-        (other.x-self.x,other.y-self.y,other.z-self.z)
+// With --verbose, report on stderr how long `what` has taken since `start`
+fn elapsed(opts: &Options, what: &str, start: Instant) {
+    if opts.verbose {
+        eprintln!("    {}: {:?}", what, start.elapsed());
     }
+}
 
-    pub fn bond_angle(ioni: &Ion, ionj: &Ion, ionk: &Ion) -> f64 {
-        let e_ji = ionj.bond_vector(&ioni);
-        let e_jk = ionj.bond_vector(&ionk);
-        ((e_ji.0*e_jk.0 + e_ji.1*e_jk.1 + e_ji.2*e_jk.2)
-        /(ionj.bond_length(&ioni)*ionj.bond_length(&ionk))).acos()
+// One line per frame for batch runs: file (and frame, for trajectories),
+// atom count, formula, the shortest and longest bonds and, with
+// --reference, the RMSD to the reference
+fn print_summary(filename: &str, frame: usize, nframes: usize, ions: &[Ion], rmsd: Option<f64>, opts: &Options) {
+    let mut line = if nframes > 1 {
+        format!("{} [frame {}]", filename, frame)
+    } else {
+        filename.to_string()
+    };
+    line.push_str(&format!("  {} atoms  {}", ions.len(), molecular_formula(ions)));
+    if let Ok(bonds) = bonds(ions) {
+        let lengths = bonds.iter().map(|&(_, _, l)| l);
+        match (lengths.clone().reduce(f64::min), lengths.reduce(f64::max)) {
+            (Some(min), Some(max)) => line.push_str(&format!(
+                "  shortest bond {:.*}  longest bond {:.*}", opts.precision, min, opts.precision, max
+            )),
+            _ => line.push_str("  no bonds"),
+        }
+    }
+    if let Some(rmsd) = rmsd {
+        line.push_str(&format!("  rmsd {:.*}", opts.precision, rmsd));
     }
+    println!("{}", line);
+}
 
-    pub fn out_of_plane_angle(ioni: &Ion,ionj: &Ion,ionk: &Ion,ionl: &Ion) -> f64 {
-        let sin_phi_jkl = Ion::bond_angle(ionj,ionk,ionl).sin();
-        let r_kj = ionk.bond_vector(&ionj);
-        let r_kl = ionk.bond_vector(&ionl);
-        let r_ki = ionk.bond_vector(&ioni);
-        let l_kj = ionk.bond_length(&ionj);
-        let l_kl = ionk.bond_length(&ionl);
-        let l_ki = ionk.bond_length(&ioni);
-        let scalar_triple = r_ki.0*(r_kj.1*r_kl.2-r_kl.1*r_kj.2)
-                          - r_ki.1*(r_kj.0*r_kl.2-r_kl.0*r_kj.2)
-                          + r_ki.2*(r_kj.0*r_kl.1-r_kl.0*r_kj.1);
-        scalar_triple/(l_kj*l_kl*l_ki*sin_phi_jkl)
+// Whether a geometry read as angstrom has no bonds at all but would have
+// them if its coordinates were bohr, as with the Crawford project inputs
+fn looks_like_bohr(ions: &[Ion]) -> bool {
+    if ions.len() < 2 || !bonds(ions).is_ok_and(|b| b.is_empty()) {
+        return false;
     }
+    let scaled: Vec<Ion> = ions.iter().map(|ion| Ion {
+        z_val: ion.z_val,
+        x: to_angstrom(ion.x, Units::Bohr),
+        y: to_angstrom(ion.y, Units::Bohr),
+        z: to_angstrom(ion.z, Units::Bohr),
+    }).collect();
+    bonds(&scaled).is_ok_and(|b| !b.is_empty())
+}
 
+// e.g. "angles: N/A (need ≥3 atoms)"
+fn not_available(opts: &Options, section: &str, err: &GeometryError) {
+    let note = match err {
+        GeometryError::TooFewAtoms { needed, .. } => format!("{}: N/A (need ≥{} atoms)", section, needed),
+        err => format!("{}: N/A ({})", section, err),
+    };
+    if opts.no_header {
+        // the note goes to stderr, leaving an empty section in its place
+        eprintln!("{}", note);
+        println!();
+    } else {
+        println!("{}", note);
+    }
 }
 
-pub fn all_bond_lengths(mol: &Vec<Ion>) -> io::Result<Vec<Vec<f64>>> {
-    if mol.len() <= 1 {
-        Err(Error::new(ErrorKind::InvalidData, "No bonds in molecule"))
 
-    } else {
-        let mut lengths = vec![vec![0.;mol.len()];mol.len()];
-
-        for i in 0..mol.len() {
-            for j in 0..i {
-                if i != j {
-                    let l = mol[i].bond_length(&mol[j]);
-                    lengths[i][j] = l;
-                    lengths[j][i] = l;
-                }
-            }
+// Every frame in a file, timed with --verbose. With --lenient a wrong atom
+// count is a warning and the atoms actually present are read.
+fn read_frames(filename: &str, opts: &Options) -> io::Result<Vec<Frame>> {
+    let start = Instant::now();
+    let input = open_input(filename).map_err(|e| in_file(filename, e))?;
+    let lines = file_to_vec(input).map_err(|e| in_file(filename, e))?;
+    let frames = if opts.lenient {
+        let (frames, mismatches) = parse_frames_lenient(&lines, opts.units).map_err(|e| in_file(filename, e))?;
+        for (frame, e) in mismatches {
+            eprintln!("warning: {}: frame {}: {}; reading the atoms present", filename, frame, e);
         }
-
-        Ok(lengths)
+        frames
+    } else {
+        parse_frames_with_comments(&lines, opts.units).map_err(|e| in_file(filename, e))?
+    };
+    if opts.verbose {
+        eprintln!("{}: read {} frame(s) in {:?}", filename, frames.len(), start.elapsed());
     }
+    Ok(frames)
 }
 
-pub fn bond_angles(mol: &Vec<Ion>) -> io::Result<Vec<(usize,usize,usize,f64)>> {
-    let len = mol.len();
-    if len <= 2 {
-        Err(Error::new(ErrorKind::InvalidData, "too few ions for bond angles"))
-    } else {
-        // length of angles is nth trigonal pyramidal number
-        // where n is len-1
-        let n_uniques = (3*(len-2).pow(2) + (len-2).pow(3) + 2*(len-2))/6;
-        let mut angles = vec![(0,0,0,0.);n_uniques];
-        let mut angle_index = 0;
-        for i in 0..mol.len() {
-            for j in 0..i {
-                for k in 0..j {
-                    if !(i==j||j==k||i==k) {
-                        angles[angle_index] = (k,j,i,Ion::bond_angle(&mol[i],&mol[j],&mol[k]));
-                        angle_index += 1;
-                    }
-                }
-            }
-        }
-        Ok(angles)
+// The --frame frame, or all of them, with their indices in the file
+fn select_frames(filename: &str, frames: Vec<Frame>, opts: &Options) -> io::Result<Vec<(usize, Frame)>> {
+    let nframes = frames.len();
+    match opts.frame {
+        Some(n) => {
+            let index = opts.frame_index(nframes).ok_or_else(|| Error::new(ErrorKind::InvalidInput, format!(
+                "{}: no frame {} (the file has {} frame{})", filename, n, nframes, if nframes == 1 { "" } else { "s" }
+            )))?;
+            Ok(vec![(index, frames.into_iter().nth(index).unwrap())])
+        },
+        None => Ok(frames.into_iter().enumerate().collect()),
     }
 }
+
+// The first geometry in a --compare or --reference file
+fn read_reference(filename: &str, opts: &Options) -> io::Result<Molecule> {
+    let mut frames = read_frames(filename, opts)?;
+    Molecule::new(frames.swap_remove(0).1).map_err(|e| in_file(filename, e))
+}
+
+// A geometry that cannot be compared with the reference, e.g. "b.xyz: does
+// not match reference a.xyz: element mismatch at ion 1 (H and O)"
+fn against(filename: &str, reference: &str, err: GeometryError) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("{}: does not match reference {}: {}", filename, reference, err))
+}
+
+// Attach the file name to a geometry or I/O error for reporting from main,
+// keeping an I/O error's kind
+fn in_file<E: Into<Error>>(filename: &str, err: E) -> Error {
+    let err = err.into();
+    Error::new(err.kind(), format!("{}: {}", filename, err))
+}
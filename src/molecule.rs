@@ -0,0 +1,42 @@
+use std::ops::Deref;
+
+use crate::error::GeometryError;
+use crate::geometry::{Ion, validate_molecule};
+
+// A geometry known to be usable: at least one atom, every atomic number
+// positive, every coordinate finite and no two atoms at the same position.
+// It dereferences to the underlying Vec<Ion>, so all the &[Ion]
+// functions take a &Molecule as is, without re-checking anything.
+// That Deref is the intended design, not a stopgap: the slice functions
+// stay the API and are not deprecated, so they also accept unchecked
+// slices and validate nothing themselves. Build a Molecule once wherever
+// the input is not already trusted.
+#[derive(Debug,Clone,PartialEq)]
+pub struct Molecule(Vec<Ion>);
+
+impl Molecule {
+
+    pub fn new(ions: Vec<Ion>) -> Result<Molecule, GeometryError> {
+        if ions.is_empty() {
+            return Err(GeometryError::TooFewAtoms { needed: 1, found: 0 });
+        }
+        if let Some(ion) = ions.iter().find(|ion| ion.z_val <= 0) {
+            return Err(GeometryError::UnknownElement(ion.z_val));
+        }
+        validate_molecule(&ions)?;
+        Ok(Molecule(ions))
+    }
+
+    pub fn into_inner(self) -> Vec<Ion> {
+        self.0
+    }
+
+}
+
+impl Deref for Molecule {
+    type Target = Vec<Ion>;
+
+    fn deref(&self) -> &Vec<Ion> {
+        &self.0
+    }
+}
@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+
+use crate::error::GeometryError;
+use crate::geometry::{BOND_TOLERANCE, Ion, covalent_radii};
+use crate::linalg::{cross, dot};
+
+// Periodic simulation cell given by its three lattice vectors, in angstrom.
+// Geometry functions without a cell treat the molecule as isolated.
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub struct UnitCell {
+    vectors: [[f64;3];3],
+    // rows are the reciprocal vectors, so inverse[n].r is the fractional
+    // coordinate of r along lattice vector n
+    inverse: [[f64;3];3],
+}
+
+impl UnitCell {
+
+    pub fn new(a: [f64;3], b: [f64;3], c: [f64;3]) -> Result<UnitCell, GeometryError> {
+        let volume = dot(a, cross(b, c));
+        if volume == 0. || !volume.is_finite() {
+            return Err(GeometryError::DegenerateCell);
+        }
+        let row = |v: [f64;3]| v.map(|x| x/volume);
+        Ok(UnitCell {
+            vectors: [a, b, c],
+            inverse: [row(cross(b, c)), row(cross(c, a)), row(cross(a, b))],
+        })
+    }
+
+    // Box with edges a, b and c along x, y and z
+    pub fn orthorhombic(a: f64, b: f64, c: f64) -> Result<UnitCell, GeometryError> {
+        UnitCell::new([a,0.,0.], [0.,b,0.], [0.,0.,c])
+    }
+
+    pub fn vectors(&self) -> [[f64;3];3] {
+        self.vectors
+    }
+
+    // Cartesian position (angstrom) of the point with fractional
+    // coordinates f, i.e. f[0]a + f[1]b + f[2]c
+    pub fn to_cartesian(&self, f: [f64;3]) -> [f64;3] {
+        let mut r = [0.;3];
+        for (n, fraction) in f.iter().enumerate() {
+            for (c, rc) in r.iter_mut().enumerate() {
+                *rc += fraction*self.vectors[n][c];
+            }
+        }
+        r
+    }
+
+    // Fractional coordinates of the Cartesian point r, the inverse of
+    // to_cartesian. Points outside the cell are not wrapped back in.
+    pub fn to_fractional(&self, r: [f64;3]) -> [f64;3] {
+        self.inverse.map(|row| row[0]*r[0] + row[1]*r[1] + row[2]*r[2])
+    }
+
+    // Shift a displacement by whole lattice vectors so each fractional
+    // component lies in [-1/2, 1/2]. For an orthorhombic box that is the
+    // minimum image; in a strongly skewed triclinic cell a shorter image
+    // can occasionally exist.
+    pub fn minimum_image(&self, d: [f64;3]) -> [f64;3] {
+        let mut d = d;
+        for n in 0..3 {
+            let frac = self.inverse[n][0]*d[0] + self.inverse[n][1]*d[1] + self.inverse[n][2]*d[2];
+            let shift = frac.round();
+            for (c, dc) in d.iter_mut().enumerate() {
+                *dc -= shift*self.vectors[n][c];
+            }
+        }
+        d
+    }
+
+    // Distance from a to the nearest periodic image of b
+    pub fn bond_length(&self, a: &Ion, b: &Ion) -> f64 {
+        let d = self.minimum_image(a.displacement(b));
+        dot(d, d).sqrt()
+    }
+
+}
+
+// Ions from (Z, fractional coordinates) pairs, as listed in crystal
+// structure files, placed in Cartesian space by the cell
+pub fn from_fractional(atoms: &[(i32,[f64;3])], cell: &UnitCell) -> Vec<Ion> {
+    atoms.iter().map(|&(z_val, f)| {
+        let [x, y, z] = cell.to_cartesian(f);
+        Ion { z_val, x, y, z }
+    }).collect()
+}
+
+// Fractional coordinates of every atom, in index order
+pub fn fractional_coordinates(mol: &[Ion], cell: &UnitCell) -> Vec<[f64;3]> {
+    mol.iter().map(|ion| cell.to_fractional([ion.x, ion.y, ion.z])).collect()
+}
+
+// all_bond_lengths under the minimum-image convention
+pub fn all_bond_lengths_periodic(mol: &[Ion], cell: &UnitCell) -> Result<Vec<Vec<f64>>, GeometryError> {
+    if mol.len() <= 1 {
+        return Err(GeometryError::TooFewAtoms { needed: 2, found: mol.len() });
+    }
+
+    let mut lengths = vec![vec![0.;mol.len()];mol.len()];
+    for i in 0..mol.len() {
+        for j in 0..i {
+            let l = cell.bond_length(&mol[i], &mol[j]);
+            lengths[i][j] = l;
+            lengths[j][i] = l;
+        }
+    }
+    Ok(lengths)
+}
+
+// bonds under the minimum-image convention, so bonds that cross a cell
+// face are found
+pub fn bonds_periodic(mol: &[Ion], cell: &UnitCell) -> Result<Vec<(usize,usize,f64)>, GeometryError> {
+    let radii = covalent_radii(mol, &HashMap::new())?;
+    let mut bonds = Vec::new();
+    for i in 0..mol.len() {
+        for j in 0..i {
+            let l = cell.bond_length(&mol[i], &mol[j]);
+            if l < BOND_TOLERANCE*(radii[i]+radii[j]) {
+                bonds.push((j,i,l));
+            }
+        }
+    }
+    Ok(bonds)
+}
@@ -0,0 +1,98 @@
+use std::f64::consts::PI;
+
+use crate::align::mean_position;
+use crate::error::GeometryError;
+use crate::geometry::{Ion, select_atoms};
+use crate::linalg::{cross, dot, eig_sym3};
+
+// Least-squares plane through the atoms: its unit normal, and the RMS
+// distance of the atoms from it (zero for a planar molecule). The plane
+// passes through the centroid; the normal is the direction of least spread,
+// with its largest component made positive so the sign is reproducible.
+pub fn best_fit_plane(mol: &[Ion]) -> Result<([f64;3], f64), GeometryError> {
+    if mol.len() < 3 {
+        return Err(GeometryError::TooFewAtoms { needed: 3, found: mol.len() });
+    }
+
+    let c = mean_position(mol);
+    let mut scatter = [[0.;3];3];
+    for ion in mol {
+        let r = [ion.x-c.0, ion.y-c.1, ion.z-c.2];
+        for a in 0..3 {
+            for b in 0..3 {
+                scatter[a][b] += r[a]*r[b];
+            }
+        }
+    }
+
+    // eigenvalues ascend, so column 0 is the normal and the smallest
+    // eigenvalue is the sum of squared distances from the plane
+    let (values, vectors) = eig_sym3(scatter);
+    let mut normal = [vectors[0][0], vectors[1][0], vectors[2][0]];
+    let dominant = normal.iter().fold(0., |d: f64, &n| if n.abs() > d.abs() { n } else { d });
+    if dominant < 0. {
+        normal = normal.map(|n| -n);
+    }
+    Ok((normal, (values[0].max(0.)/mol.len() as f64).sqrt()))
+}
+
+// Smallest axis-aligned box containing every atom, as (min, max) corners
+pub fn bounding_box(mol: &[Ion]) -> Result<([f64;3],[f64;3]), GeometryError> {
+    if mol.is_empty() {
+        return Err(GeometryError::TooFewAtoms { needed: 1, found: 0 });
+    }
+    let mut min = [f64::INFINITY;3];
+    let mut max = [f64::NEG_INFINITY;3];
+    for ion in mol {
+        for (c, r) in [ion.x, ion.y, ion.z].iter().enumerate() {
+            min[c] = min[c].min(*r);
+            max[c] = max[c].max(*r);
+        }
+    }
+    Ok((min, max))
+}
+
+// Span of the atoms along x, y and z
+pub fn extent(mol: &[Ion]) -> Result<[f64;3], GeometryError> {
+    let (min, max) = bounding_box(mol)?;
+    Ok([max[0]-min[0], max[1]-min[1], max[2]-min[2]])
+}
+
+// Cremer-Pople puckering of a five-membered ring, the atoms of `ring` taken
+// in bonding order: the amplitude Q (angstrom) and phase angle phi (radians,
+// in [0, 2pi)). Displacements are measured from the Cremer-Pople mean plane,
+// along the normal R'xR'' of the original paper, which points away from a
+// viewer who sees the ring order run anticlockwise. A planar ring has Q = 0;
+// an envelope with ring[0] as the flap has phi = 0 when the flap lies on the
+// normal's side of the plane and pi when it lies on the other.
+pub fn ring_pucker(mol: &[Ion], ring: &[usize;5]) -> Result<(f64, f64), GeometryError> {
+    let atoms = select_atoms(mol, ring)?;
+    let c = mean_position(&atoms);
+    let r: Vec<[f64;3]> = atoms.iter().map(|ion| [ion.x-c.0, ion.y-c.1, ion.z-c.2]).collect();
+    let n = r.len() as f64;
+    let phase = |j: usize, m: f64| 2.*PI*m*j as f64/n;
+
+    let mut r1 = [0.;3];
+    let mut r2 = [0.;3];
+    for (j, rj) in r.iter().enumerate() {
+        let (s, c) = (phase(j, 1.).sin(), phase(j, 1.).cos());
+        for a in 0..3 {
+            r1[a] += rj[a]*s;
+            r2[a] += rj[a]*c;
+        }
+    }
+    let normal = cross(r1, r2);
+    let length = dot(normal, normal).sqrt();
+    if length <= 1e-12 {
+        return Err(GeometryError::CollinearAtoms);
+    }
+    let normal = normal.map(|x| x/length);
+    let z: Vec<f64> = r.iter().map(|&rj| dot(rj, normal)).collect();
+
+    // a five-membered ring has the single puckering pair q2, phi2, so Q = q2
+    let scale = (2./n).sqrt();
+    let q_cos = scale*z.iter().enumerate().map(|(j, zj)| zj*phase(j, 2.).cos()).sum::<f64>();
+    let q_sin = -scale*z.iter().enumerate().map(|(j, zj)| zj*phase(j, 2.).sin()).sum::<f64>();
+    let amplitude = z.iter().map(|zj| zj*zj).sum::<f64>().sqrt();
+    Ok((amplitude, q_sin.atan2(q_cos).rem_euclid(2.*PI)))
+}
@@ -0,0 +1,21 @@
+use crate::align::mean_position;
+use crate::geometry::Ion;
+
+// True when inverting through the centroid maps every atom onto an atom of
+// the same element within `tol` (angstrom). An atom at the center is its
+// own partner. An empty molecule has no inversion center.
+pub fn has_inversion_center(mol: &[Ion], tol: f64) -> bool {
+    if mol.is_empty() {
+        return false;
+    }
+    let c = mean_position(mol);
+    mol.iter().all(|ion| {
+        let image = Ion {
+            z_val: ion.z_val,
+            x: 2.*c.0 - ion.x,
+            y: 2.*c.1 - ion.y,
+            z: 2.*c.2 - ion.z,
+        };
+        mol.iter().any(|other| other.z_val == image.z_val && other.bond_length_sq(&image) <= tol*tol)
+    })
+}
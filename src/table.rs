@@ -0,0 +1,64 @@
+use crawford_group_projects::Ion;
+use crawford_group_projects::elements::symbol_for_z;
+
+// Element symbol, falling back to Z for unknown elements
+pub fn element_label(z: i32) -> String {
+    symbol_for_z(z).map_or_else(|| z.to_string(), String::from)
+}
+
+// Label atoms as "index symbol"
+pub fn atom_label(i: usize, ion: &Ion) -> String {
+    format!("{} {}", i, element_label(ion.z_val))
+}
+
+// Render a square matrix (as from all_bond_lengths) with `labels` on both
+// axes, `precision` decimal places and right-aligned columns. Each line
+// starts with `indent`.
+pub fn distance_matrix(labels: &[String], matrix: &[Vec<f64>], precision: usize, indent: &str) -> String {
+    let cells: Vec<Vec<String>> = matrix.iter()
+        .map(|row| row.iter().map(|l| format!("{:.*}", precision, l)).collect())
+        .collect();
+
+    let label_width = labels.iter().map(|l| l.len()).max().unwrap_or(0);
+    let width = cells.iter().flatten().map(|c| c.len())
+        .chain(labels.iter().map(|l| l.len()))
+        .max()
+        .unwrap_or(0);
+
+    let mut out = String::new();
+    out.push_str(indent);
+    out.push_str(&" ".repeat(label_width));
+    for label in labels {
+        out.push_str(&format!("  {:>w$}", label, w = width));
+    }
+    out.push('\n');
+    for (label, row) in labels.iter().zip(&cells) {
+        out.push_str(indent);
+        out.push_str(&format!("{:<w$}", label, w = label_width));
+        for cell in row {
+            out.push_str(&format!("  {:>w$}", cell, w = width));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+// Lay out rows of cells as right-aligned columns separated by two spaces,
+// each line starting with `indent`
+pub fn columns(rows: &[Vec<String>], indent: &str) -> String {
+    let ncols = rows.iter().map(|r| r.len()).max().unwrap_or(0);
+    let widths: Vec<usize> = (0..ncols)
+        .map(|c| rows.iter().filter_map(|r| r.get(c)).map(|cell| cell.chars().count()).max().unwrap_or(0))
+        .collect();
+
+    let mut out = String::new();
+    for row in rows {
+        out.push_str(indent);
+        let cells: Vec<String> = row.iter().zip(&widths)
+            .map(|(cell, &w)| format!("{:>w$}", cell, w = w))
+            .collect();
+        out.push_str(&cells.join("  "));
+        out.push('\n');
+    }
+    out
+}